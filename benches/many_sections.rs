@@ -0,0 +1,85 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Time the parsing, lookup, call graph and printing of a synthetic disassembly with thousands of sections, as printed
+//! for a large statically linked executable.
+//!
+//! Run with `cargo bench --bench many_sections`.
+
+use std::time::{Duration, Instant};
+
+use disasm_util::{Disasm, ParseOptions};
+
+const SECTIONS: usize = 5000;
+const ITERATIONS: u32 = 10;
+
+/// Objdump output with `SECTIONS` sections, each header repeated twice in an interleaved order.
+///
+/// Every symbol calls the one of the previous section, giving as many distinct call edges.
+fn synthetic_lines() -> Vec<String> {
+    let mut lines = vec![String::from("file.o:     file format elf64-x86-64")];
+    for round in 0..2 {
+        for idx in 0..SECTIONS {
+            lines.push(format!("Disassembly of section .text.f{idx}:"));
+            lines.push(format!("<f{idx}_{round}>:"));
+            lines.push(String::from("\tpush   %rbp"));
+            lines.push(String::from("\tmov    %rsp,%rbp"));
+            lines.push(format!("\tcall   <f{}_{round}>", idx.saturating_sub(1)));
+            lines.push(String::from("\tret"));
+        }
+    }
+    lines
+}
+
+/// Run `routine` `ITERATIONS` times and print its mean duration.
+fn bench<T>(name: &str, mut routine: impl FnMut() -> T) {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let result = routine();
+        total += start.elapsed();
+        drop(result);
+    }
+    println!("{name:<32} {:>10.3?}", total / ITERATIONS);
+}
+
+fn main() {
+    let lines = synthetic_lines();
+    let parse = |options: &ParseOptions| {
+        Disasm::from_lines(lines.iter().map(String::as_str), options).unwrap()
+    };
+    let merge_options = ParseOptions {
+        merge_duplicate_sections: true,
+        ..Default::default()
+    };
+
+    bench("parse", || parse(&ParseOptions::default()));
+    bench("parse merging duplicates", || parse(&merge_options));
+
+    let disasm = parse(&merge_options);
+    assert_eq!(disasm.len(), SECTIONS);
+    bench("section_by_name", || {
+        (0..SECTIONS)
+            .filter_map(|idx| disasm.section_by_name(&format!(".text.f{idx}")))
+            .count()
+    });
+    bench("call_edges", || disasm.call_edges());
+    bench("to_string", || disasm.to_string());
+}
@@ -23,433 +23,3999 @@
 //! This module contains the Disasm struct which can be used to parse the output file of a objdump command.
 //! This file operates over files generated with the following combination of flags:
 //! objdump -d --no-addresses --no-show-raw-insn
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
 
+mod demangle;
+mod diff;
+mod error;
+mod file_format;
+mod format_options;
+mod hash;
 mod instruction;
+mod markdown;
+mod parse_options;
+mod parse_stats;
 mod section;
+mod style;
+mod summary;
 mod symbol;
 
-use instruction::Instruction;
-use section::Section;
-use symbol::Symbol;
+pub use demangle::Demangler;
+pub use diff::{DiffReport, SectionDiff};
+pub use error::DisasmError;
+pub use file_format::FileFormat;
+pub use format_options::FormatOptions;
+pub use hash::Fnv1a;
+pub use instruction::{BranchTarget, Instruction, InstructionKind, OpcodeCategory};
+pub use parse_options::{Backend, ParseOptions, SortKey};
+pub use parse_stats::ParseStats;
+pub use section::Section;
+pub use summary::{SectionStat, Summary};
+pub use symbol::{Symbol, SymbolRef};
 
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::Serialize;
+use style::{ColorWriter, StyledWrite};
+use termcolor::{NoColor, WriteColor};
 
-#[derive(Debug, PartialEq, Eq)]
+/// Pattern matching the names of the sections usually containing code, such as `.text` or `.text.unlikely`.
+pub const CODE_SECTIONS_PATTERN: &str = r"^\.(text|plt)(\..+)?$|^\.(init|fini)$";
+
+/// Prefixes printed by objdump before the mnemonic of an instruction, such as `lock` or `{vex}`.
+///
+/// The opcode is made of the prefixes and the mnemonic, so that an operand without spaces is never taken for a second
+/// word of the opcode.
+const INSTRUCTION_PREFIXES: &str = r"bnd|cs|ds|es|fs|gs|ss|lock|notrack|rep|repe|repne|repnz|repz|data16|addr32|
+    xacquire|xrelease|rex64|rex(\.[WRXB]+)?|\{vex3?\}|\{evex\}";
+
+/// Parsed output of an objdump disassembly, made of a list of sections.
+#[derive(Debug, Serialize)]
 pub struct Disasm {
     file_name: String,
     file_format: String,
     sections: Vec<Section>,
+    #[serde(skip)]
+    skipped_lines: Vec<(usize, String)>,
+    /// Position of the first section with each name, rebuilt whenever the sections change.
+    #[serde(skip)]
+    section_index: HashMap<String, usize>,
+    /// Position of the section the following symbols and instructions are added to.
+    #[serde(skip)]
+    current_section: Option<usize>,
+    #[serde(skip)]
+    parse_stats: ParseStats,
+}
+
+/// Kind of a line recognized while parsing, counted in the [`ParseStats`].
+enum LineKind {
+    Section,
+    Symbol,
+    Instruction,
+    Other,
 }
 
+// The section index and the parsing statistics don't describe the content, so they are left out of the comparison
+impl PartialEq for Disasm {
+    fn eq(&self, other: &Self) -> bool {
+        self.file_name == other.file_name
+            && self.file_format == other.file_format
+            && self.sections == other.sections
+            && self.skipped_lines == other.skipped_lines
+    }
+}
+
+impl Eq for Disasm {}
+
 impl Disasm {
-    fn process_first_line(&mut self, line: &str) -> Result<(), String> {
-        let err_msg = "Incorrect format for the first line";
+    /// Create an empty disassembly, to be filled with [`Disasm::add_section`] and the other `add_*` methods.
+    ///
+    /// Parsing the output of objdump is the main way to build a disassembly, this allows assembling one directly.
+    ///
+    /// ```
+    /// use disasm_util::{Disasm, Instruction, Section, Symbol};
+    ///
+    /// let mut disasm = Disasm::new("file.o", "elf64-x86-64");
+    /// disasm.add_section(Section::new(".init"));
+    /// disasm.add_symbol(Symbol::new("<_init>")).unwrap();
+    /// disasm.add_instruction(Instruction::new("ret", "", "")).unwrap();
+    /// disasm.add_section(Section::new(".text"));
+    /// disasm.add_symbol(Symbol::new("<main>")).unwrap();
+    /// disasm.add_instruction(Instruction::new("push", "%rbp", "")).unwrap();
+    /// disasm.add_instruction(Instruction::new("ret", "", "")).unwrap();
+    ///
+    /// assert_eq!(
+    ///     disasm.to_string(),
+    ///     ".init:\n    <_init>:\n        ret\n.text:\n    <main>:\n        push\n        ret\n"
+    /// );
+    /// ```
+    pub fn new(file_name: &str, file_format: &str) -> Self {
+        Disasm {
+            file_name: file_name.to_string(),
+            file_format: file_format.to_string(),
+            sections: Vec::new(),
+            skipped_lines: Vec::new(),
+            section_index: HashMap::new(),
+            current_section: None,
+            parse_stats: ParseStats::default(),
+        }
+    }
+
+    /// Parse the lines of an objdump disassembly.
+    ///
+    /// Parsing stops at the first unrecognized line unless `options` enables the lenient mode, in which case
+    /// unrecognized lines are skipped and stored together with their line number.
+    pub fn from_lines<'a, I>(lines: I, options: &ParseOptions) -> Result<Self, DisasmError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        Self::parse_lines(lines.into_iter().map(Ok), options)
+    }
+
+    /// Parse an objdump disassembly from `reader`, processing each line as soon as it is read.
+    ///
+    /// Behaves like [`Disasm::from_lines`], without holding the whole input in memory.
+    pub fn from_reader<R: BufRead>(reader: R, options: &ParseOptions) -> Result<Self, DisasmError> {
+        Self::parse_lines(
+            reader.lines().map(|line| line.map_err(DisasmError::from)),
+            options,
+        )
+    }
+
+    fn parse_lines<I, S>(lines: I, options: &ParseOptions) -> Result<Self, DisasmError>
+    where
+        I: Iterator<Item = Result<S, DisasmError>>,
+        S: AsRef<str>,
+    {
+        let mut disasm = Disasm::new("", "");
+        // Number the lines starting from one, then filter out empty lines
+        let mut lines_iter =
+            lines
+                .enumerate()
+                .map(|(idx, line)| (idx + 1, line))
+                .filter(|(_, line)| match line {
+                    Ok(line) => !line.as_ref().trim().is_empty(),
+                    Err(_) => true,
+                });
+        // Process the file format line, skipping the banners objdump may print before it
+        let mut first_error = None;
+        loop {
+            let (line_no, line) = match lines_iter.next() {
+                Some(next) => next,
+                None => return Err(first_error.unwrap_or(DisasmError::EmptyInput)),
+            };
+            match line.and_then(|line| disasm.process_first_line(line.as_ref())) {
+                Ok(()) => break,
+                Err(DisasmError::BadFirstLine) => {
+                    if first_error.is_none() {
+                        first_error = Some(DisasmError::BadFirstLine.at_line(line_no));
+                    }
+                }
+                Err(error) => return Err(error.at_line(line_no)),
+            }
+        }
+        disasm.parse_stats.other += 1;
+        // Process all other lines
+        let regexes = SyntaxRegexes::for_options(options);
+        let mut section_table = Vec::new();
+        for (line_no, line) in lines_iter {
+            match line.and_then(|line| {
+                // The section headers, printed with `-h`, come before the disassembly
+                if disasm.sections.is_empty()
+                    && Disasm::process_section_table_line(line.as_ref(), &mut section_table)
+                {
+                    return Ok(LineKind::Other);
+                }
+                disasm.process_other_line(line.as_ref(), &regexes, options)
+            }) {
+                Ok(LineKind::Section) => disasm.parse_stats.sections += 1,
+                Ok(LineKind::Symbol) => disasm.parse_stats.symbols += 1,
+                Ok(LineKind::Instruction) => disasm.parse_stats.instructions += 1,
+                Ok(LineKind::Other) => disasm.parse_stats.other += 1,
+                Err(DisasmError::UnrecognizedLine(line)) if options.lenient => {
+                    disasm.parse_stats.skipped += 1;
+                    disasm.skipped_lines.push((line_no, line))
+                }
+                Err(error) => return Err(error.at_line(line_no)),
+            }
+        }
+        let section_flags: HashMap<String, Vec<String>> = section_table.into_iter().collect();
+        for section in &mut disasm.sections {
+            if let Some(flags) = section_flags.get(section.get_name()) {
+                section.set_flags(flags.clone());
+            }
+        }
+        // Sort the stored data
+        if !options.keep_order {
+            disasm.sort_with(options.sort_by, options.case_insensitive_sort);
+        }
+        Ok(disasm)
+    }
+
+    pub fn get_file_name(&self) -> &String {
+        &self.file_name
+    }
+
+    /// Last component of the file name, splitting it on both Unix and Windows path separators.
+    pub fn file_base_name(&self) -> &str {
+        self.file_name
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Replace both Unix and Windows path separators in the file name with `separator`.
+    pub fn normalize_path_separator(&mut self, separator: char) {
+        self.file_name = self.file_name.replace(['/', '\\'], &separator.to_string());
+    }
+
+    /// File format as printed by objdump, such as `elf64-x86-64`.
+    pub fn get_file_format(&self) -> &String {
+        &self.file_format
+    }
+
+    /// File format parsed into its object format and architecture.
+    pub fn parsed_file_format(&self) -> FileFormat {
+        FileFormat::from(self.file_format.as_str())
+    }
+
+    /// First section named `name`, found through an index of the section names.
+    pub fn section_by_name(&self, name: &str) -> Option<&Section> {
+        self.section_index.get(name).map(|idx| &self.sections[*idx])
+    }
+
+    /// Iterate over the sections of the disassembly.
+    ///
+    /// ```
+    /// use disasm_util::Disasm;
+    ///
+    /// let text = "file.o:     file format elf64-x86-64\n\
+    ///             Disassembly of section .init:\n\
+    ///             <_init>:\n\
+    ///             \tendbr64\n\
+    ///             \tret\n\
+    ///             Disassembly of section .text:\n\
+    ///             <main>:\n\
+    ///             \tret\n";
+    /// let disasm = Disasm::try_from(text.to_string()).unwrap();
+    /// let total_instructions: usize = disasm
+    ///     .sections()
+    ///     .flat_map(|section| section.symbols())
+    ///     .map(|symbol| symbol.len())
+    ///     .sum();
+    /// assert_eq!(total_instructions, 3);
+    /// ```
+    pub fn sections(&self) -> impl Iterator<Item = &Section> {
+        self.sections.iter()
+    }
+
+    /// Number of sections in the disassembly.
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Check if the disassembly contains no sections.
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Keep only the sections whose name is contained in `names`.
+    pub fn retain_sections(&mut self, names: &[String]) {
+        self.sections.retain(|sec| names.contains(sec.get_name()));
+        self.reindex();
+    }
+
+    /// Keep only the sections whose name matches `regex`.
+    pub fn retain_sections_matching(&mut self, regex: &Regex) {
+        self.sections.retain(|sec| regex.is_match(sec.get_name()));
+        self.reindex();
+    }
+
+    /// Keep only the symbols whose name matches `regex`, in all sections.
+    pub fn retain_symbols_matching(&mut self, regex: &Regex) {
+        for section in &mut self.sections {
+            section.retain_symbols_matching(regex);
+        }
+    }
+
+    /// Keep only the instructions whose opcode is contained in `opcodes`, in all symbols.
+    pub fn retain_instructions_matching(&mut self, opcodes: &[String]) {
+        for symbol in self.sections.iter_mut().flat_map(Section::symbols_mut) {
+            symbol.retain_instructions_matching(opcodes);
+        }
+    }
+
+    /// Sort the instructions of every symbol by opcode, operands and comment.
+    pub fn sort_instructions(&mut self) {
+        for symbol in self.sections.iter_mut().flat_map(Section::symbols_mut) {
+            symbol.sort_instructions();
+        }
+    }
+
+    /// Remove all the symbols not containing any instruction, in all sections.
+    pub fn drop_empty_symbols(&mut self) {
+        for section in &mut self.sections {
+            section.drop_empty_symbols();
+        }
+    }
+
+    /// Remove all the sections not containing any symbol.
+    pub fn drop_empty_sections(&mut self) {
+        self.sections.retain(|sec| !sec.is_empty());
+        self.reindex();
+    }
+
+    /// Names of the sections appearing more than once, each listed once.
+    pub fn find_duplicate_sections(&self) -> Vec<&str> {
+        section::duplicate_names(
+            self.sections
+                .iter()
+                .map(|section| section.get_name().as_str()),
+        )
+    }
+
+    /// Lines skipped while parsing in lenient mode, each with its line number in the input.
+    pub fn skipped_lines(&self) -> &[(usize, String)] {
+        &self.skipped_lines
+    }
+
+    /// Number of lines of each kind met while parsing the disassembly.
+    pub fn parse_stats(&self) -> &ParseStats {
+        &self.parse_stats
+    }
+
+    /// Count the sections, symbols and instructions of the disassembly.
+    pub fn summary(&self) -> Summary {
+        let symbols: Vec<&Symbol> = self
+            .sections
+            .iter()
+            .flat_map(|section| section.symbols())
+            .collect();
+        Summary {
+            file_name: self.file_name.clone(),
+            file_format: self.file_format.clone(),
+            sections: self.sections.len(),
+            symbols: symbols.len(),
+            instructions: symbols
+                .iter()
+                .map(|symbol| symbol.instruction_count())
+                .sum(),
+        }
+    }
+
+    /// Replace the hex addresses in the operands and comments of all instructions with a placeholder.
+    ///
+    /// Identical code placed at different addresses then produces the same output.
+    pub fn normalize_addresses(&mut self) {
+        for instruction in self
+            .sections
+            .iter_mut()
+            .flat_map(|section| section.symbols_mut())
+            .flat_map(|symbol| symbol.instructions_mut())
+        {
+            instruction.normalize_addresses();
+        }
+    }
+
+    /// Replace the mnemonics spelled differently by some objdump versions, such as `retq`, with their canonical form.
+    pub fn canonicalize_mnemonics(&mut self) {
+        for instruction in self
+            .sections
+            .iter_mut()
+            .flat_map(|section| section.symbols_mut())
+            .flat_map(|symbol| symbol.instructions_mut())
+        {
+            instruction.canonicalize_opcode();
+        }
+    }
+
+    /// Count the instructions of each [`OpcodeCategory`], ignoring empty opcodes and directives.
+    pub fn category_stats(&self) -> BTreeMap<OpcodeCategory, usize> {
+        let mut stats = BTreeMap::new();
+        for instruction in self
+            .sections
+            .iter()
+            .flat_map(|section| section.symbols())
+            .flat_map(|symbol| symbol.instructions())
+            .filter(|instruction| instruction.kind() == InstructionKind::Opcode)
+            .filter(|instruction| !instruction.opcode().is_empty())
+        {
+            *stats.entry(instruction.category()).or_insert(0) += 1;
+        }
+        stats
+    }
+
+    /// Count the occurrences of each opcode over all the instructions, ignoring empty opcodes and directives.
+    pub fn opcode_histogram(&self) -> BTreeMap<String, usize> {
+        let mut histogram = BTreeMap::new();
+        for instruction in self
+            .sections
+            .iter()
+            .flat_map(|section| section.symbols())
+            .flat_map(|symbol| symbol.instructions())
+            .filter(|instruction| instruction.kind() == InstructionKind::Opcode)
+            .filter(|instruction| !instruction.opcode().is_empty())
+        {
+            *histogram
+                .entry(instruction.opcode().to_string())
+                .or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Iterate over all the symbols, yielding the section name, the symbol name and the number of instructions.
+    pub fn instruction_counts(&self) -> impl Iterator<Item = (&str, &str, usize)> {
+        self.sections.iter().flat_map(|section| {
+            section.symbols().map(move |symbol| {
+                (
+                    section.get_name().as_str(),
+                    symbol.get_name().as_str(),
+                    symbol.instruction_count(),
+                )
+            })
+        })
+    }
+
+    /// Iterate over all the symbols, yielding the section name and the symbol name.
+    pub fn iter_symbols(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.sections.iter().flat_map(|section| {
+            section
+                .symbols()
+                .map(move |symbol| (section.get_name().as_str(), symbol.get_name().as_str()))
+        })
+    }
+
+    /// Compare the symbols of this disassembly with the ones of a newer disassembly `other`.
+    ///
+    /// Symbols are matched by section and name, and are considered changed when their sequences of opcodes differ.
+    /// Symbols repeated with the same name are matched in order of appearance, so an extra copy on either side is
+    /// reported as added or removed.
+    pub fn diff(&self, other: &Disasm) -> DiffReport {
+        let opcodes = |symbol: &Symbol| {
+            symbol
+                .instructions()
+                .map(|instruction| instruction.opcode().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let names = self
+            .sections
+            .iter()
+            .chain(other.sections.iter())
+            .map(|sec| sec.get_name())
+            .collect::<BTreeSet<_>>();
+        let mut report = DiffReport::new();
+        for name in names {
+            let old_symbols = self.section_symbols(name);
+            let new_symbols = other.section_symbols(name);
+            // Symbols with a repeated name are matched by position, the n-th old one with the n-th new one
+            let mut new_by_name: HashMap<&str, VecDeque<&Symbol>> = HashMap::new();
+            for symbol in &new_symbols {
+                new_by_name
+                    .entry(symbol.get_name())
+                    .or_default()
+                    .push_back(symbol);
+            }
+            let mut old_counts: HashMap<&str, usize> = HashMap::new();
+            let mut section_diff = SectionDiff::new(name);
+            for old_symbol in &old_symbols {
+                *old_counts.entry(old_symbol.get_name()).or_default() += 1;
+                let new_symbol = new_by_name
+                    .get_mut(old_symbol.get_name().as_str())
+                    .and_then(VecDeque::pop_front);
+                match new_symbol {
+                    None => section_diff.add_removed(old_symbol.get_name()),
+                    Some(new_symbol) if opcodes(old_symbol) != opcodes(new_symbol) => {
+                        section_diff.add_changed(old_symbol.get_name())
+                    }
+                    Some(_) => {}
+                }
+            }
+            for new_symbol in &new_symbols {
+                match old_counts.get_mut(new_symbol.get_name().as_str()) {
+                    Some(count) if *count > 0 => *count -= 1,
+                    _ => section_diff.add_added(new_symbol.get_name()),
+                }
+            }
+            report.add_section(section_diff);
+        }
+        report
+    }
+
+    /// Content hash of each symbol, as computed by [`Symbol::content_hash`], keyed by `section/symbol`.
+    pub fn symbol_hashes(&self, include_operands: bool) -> BTreeMap<String, u64> {
+        let mut hashes = BTreeMap::new();
+        for section in &self.sections {
+            for symbol in section.symbols() {
+                let key = format!("{}/{}", section.get_name(), symbol.get_name());
+                hashes.insert(key, symbol.content_hash(include_operands));
+            }
+        }
+        hashes
+    }
+
+    /// Compare the symbol hashes of a previous run, from [`Disasm::symbol_hashes`], with the current ones.
+    pub fn diff_hashes(
+        &self,
+        previous: &BTreeMap<String, u64>,
+        include_operands: bool,
+    ) -> DiffReport {
+        let current = self.symbol_hashes(include_operands);
+        let split = Disasm::split_symbol_key;
+        let names = previous
+            .keys()
+            .chain(current.keys())
+            .map(|key| split(key).0)
+            .collect::<BTreeSet<_>>();
+        let mut report = DiffReport::new();
+        for name in names {
+            let mut section_diff = SectionDiff::new(name);
+            for (key, hash) in previous.iter().filter(|(key, _)| split(key).0 == name) {
+                match current.get(key) {
+                    None => section_diff.add_removed(split(key).1),
+                    Some(current_hash) if current_hash != hash => {
+                        section_diff.add_changed(split(key).1)
+                    }
+                    Some(_) => {}
+                }
+            }
+            for key in current.keys().filter(|key| split(key).0 == name) {
+                if !previous.contains_key(key) {
+                    section_diff.add_added(split(key).1);
+                }
+            }
+            report.add_section(section_diff);
+        }
+        report
+    }
+
+    /// Split a `section/symbol` key, section names never contain a slash while symbol names might.
+    fn split_symbol_key(key: &str) -> (&str, &str) {
+        key.split_once('/').unwrap_or(("", key))
+    }
+
+    /// Count the symbols and instructions of each section, largest sections first.
+    ///
+    /// Sections with the same number of instructions are kept in the disassembly order.
+    pub fn section_stats(&self) -> Vec<SectionStat> {
+        let mut stats = self
+            .sections
+            .iter()
+            .map(|section| SectionStat {
+                name: section.get_name().clone(),
+                symbols: section.len(),
+                instructions: section
+                    .symbols()
+                    .map(|symbol| symbol.instruction_count())
+                    .sum(),
+                bytes: section
+                    .symbols()
+                    .filter(|symbol| symbol.instruction_count() > 0)
+                    .map(|symbol| symbol.byte_size())
+                    .sum(),
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by_key(|stat| Reverse(stat.instructions));
+        stats
+    }
+
+    /// Names of the sections, sorted alphabetically.
+    pub fn section_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .sections
+            .iter()
+            .map(|section| section.get_name().as_str())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Group the instructions of the symbols by name, regardless of their section.
+    ///
+    /// Symbols with the same name are merged, whether they are in different sections, such as a function split
+    /// between `.text` and `.text.hot`, or repeated in the same section. Their instructions are concatenated in the
+    /// order of the sections and symbols.
+    pub fn regroup_by_symbol(&self) -> BTreeMap<String, Vec<&Instruction>> {
+        let mut groups: BTreeMap<String, Vec<&Instruction>> = BTreeMap::new();
+        for symbol in self.sections.iter().flat_map(|section| section.symbols()) {
+            groups
+                .entry(symbol.get_name().clone())
+                .or_default()
+                .extend(symbol.instructions());
+        }
+        groups
+    }
+
+    /// Section and name of every symbol containing no instructions.
+    pub fn empty_symbols(&self) -> Vec<(String, String)> {
+        self.sections
+            .iter()
+            .flat_map(|section| {
+                section
+                    .symbols()
+                    .filter(|symbol| symbol.is_empty())
+                    .map(|symbol| (section.get_name().clone(), symbol.get_name().clone()))
+            })
+            .collect()
+    }
+
+    /// Group the symbols with the same sequence of opcodes, ignoring operands and addresses.
+    ///
+    /// Only groups of two or more symbols are returned, in the order of their first symbol. Symbols without
+    /// instructions are ignored.
+    pub fn clone_clusters(&self) -> Vec<Vec<SymbolRef<'_>>> {
+        let mut clusters: Vec<Vec<SymbolRef>> = Vec::new();
+        let mut cluster_idx: BTreeMap<Vec<&str>, usize> = BTreeMap::new();
+        for section in &self.sections {
+            for symbol in section.symbols().filter(|symbol| !symbol.is_empty()) {
+                let opcodes = symbol
+                    .instructions()
+                    .map(|instruction| instruction.opcode())
+                    .collect();
+                let symbol_ref = SymbolRef {
+                    section: section.get_name(),
+                    symbol,
+                };
+                match cluster_idx.get(&opcodes) {
+                    Some(&idx) => clusters[idx].push(symbol_ref),
+                    None => {
+                        cluster_idx.insert(opcodes, clusters.len());
+                        clusters.push(vec![symbol_ref]);
+                    }
+                }
+            }
+        }
+        clusters.retain(|cluster| cluster.len() > 1);
+        clusters
+    }
+
+    /// Merge the disassemblies `others` into this one.
+    ///
+    /// Sections with the same name are merged by concatenating their symbols, while new sections are appended. The
+    /// merged disassembly is not sorted again, use [`Disasm::sort`] to sort it after merging.
+    pub fn merge(&mut self, others: Vec<Disasm>) {
+        for other in others {
+            self.file_name = format!("{}, {}", self.file_name, other.file_name);
+            if self.file_format != other.file_format {
+                self.file_format = format!("{}, {}", self.file_format, other.file_format);
+            }
+            for section in other.sections {
+                match self.section_index.get(section.get_name()) {
+                    Some(idx) => self.sections[*idx].append_symbols(section),
+                    None => self.push_section(section),
+                }
+            }
+            self.skipped_lines.extend(other.skipped_lines);
+            self.parse_stats.add(&other.parse_stats);
+        }
+    }
+
+    /// Collect the unique references from each symbol to the symbols named in its operands, such as `call <foo>`.
+    ///
+    /// Targets are returned without their offset, branches from a symbol to an offset inside itself are ignored.
+    /// Bare address targets, such as `call 0x4011a0`, are returned as the address.
+    pub fn call_edges(&self) -> Vec<(String, String)> {
+        let mut edges = Vec::new();
+        // The vector keeps the edges in order of appearance, the set avoids scanning it for each target
+        let mut seen = HashSet::new();
+        for symbol in self.sections.iter().flat_map(|section| section.symbols()) {
+            for target in symbol
+                .instructions()
+                .filter_map(|instruction| instruction.target())
+            {
+                // Bare addresses are unnamed locations, named after the address itself
+                let target_name = match &target {
+                    BranchTarget::Symbol { symbol, .. } => format!("<{symbol}>"),
+                    BranchTarget::Address(address) => format!("{address:#x}"),
+                };
+                let local_branch = target.offset().is_some() && &target_name == symbol.get_name();
+                let edge = (symbol.get_name().clone(), target_name);
+                if !local_branch && seen.insert(edge.clone()) {
+                    edges.push(edge);
+                }
+            }
+        }
+        edges
+    }
 
-        let (file_name, leftover_line) = line.split_once(':').ok_or(err_msg.to_string())?;
+    /// Symbols of all the sections named `name`, including the repeated sections that were not merged.
+    fn section_symbols(&self, name: &str) -> Vec<&Symbol> {
+        self.sections
+            .iter()
+            .filter(|sec| sec.get_name() == name)
+            .flat_map(Section::symbols)
+            .collect()
+    }
+
+    /// Append `section`, indexing it if it is the first one with its name, and make it the current section.
+    fn push_section(&mut self, section: Section) {
+        self.section_index
+            .entry(section.get_name().clone())
+            .or_insert(self.sections.len());
+        self.current_section = Some(self.sections.len());
+        self.sections.push(section);
+    }
+
+    /// Rebuild the index of the section names after the sections were removed or reordered.
+    ///
+    /// The last section becomes the current one.
+    fn reindex(&mut self) {
+        self.section_index.clear();
+        for (idx, section) in self.sections.iter().enumerate() {
+            self.section_index
+                .entry(section.get_name().clone())
+                .or_insert(idx);
+        }
+        self.current_section = self.sections.len().checked_sub(1);
+    }
+
+    fn current_section_mut(&mut self) -> Option<&mut Section> {
+        self.sections.get_mut(self.current_section?)
+    }
+
+    fn process_first_line(&mut self, line: &str) -> Result<(), DisasmError> {
+        // Windows paths may start with a drive letter followed by a colon
+        let (file_name, leftover_line) = line.rsplit_once(':').ok_or(DisasmError::BadFirstLine)?;
         self.file_name = file_name.to_string();
         self.file_format = leftover_line
             .trim()
             .strip_prefix("file format ")
-            .ok_or(err_msg.to_string())?
+            .ok_or(DisasmError::BadFirstLine)?
             .to_string();
         Ok(())
     }
 
-    fn process_other_line(&mut self, line: &str) -> Result<(), String> {
+    /// Process a line of the section headers table, collecting the flags of each section.
+    ///
+    /// GNU objdump prints the flags on the line following each section, llvm-objdump prints the section type at the
+    /// end of the same line. Returns `false` when the line is not part of the table.
+    fn process_section_table_line(line: &str, table: &mut Vec<(String, Vec<String>)>) -> bool {
+        lazy_static! {
+            static ref RE_TABLE_HEADER: Regex =
+                Regex::new(r"^(Sections:|Idx[[:space:]]+Name)").unwrap();
+            static ref RE_TABLE_ENTRY: Regex = Regex::new(
+                r"(?x)^
+                    [[:space:]]*[[:digit:]]+[[:space:]]+
+                    (?P<name>[^[:space:]]*)
+                    ([[:space:]]+[[:xdigit:]]+){2,}
+                    ([[:space:]]+2\*\*[[:digit:]]+)?
+                    ([[:space:]]+(?P<flags>[A-Z][A-Z_[:space:]]*?))?
+                    [[:space:]]*$"
+            )
+            .unwrap();
+            static ref RE_TABLE_FLAGS: Regex =
+                Regex::new(r"^[[:space:]]+(?P<flags>[A-Z_]+(,[[:space:]]*[A-Z_]+)*)[[:space:]]*$")
+                    .unwrap();
+        }
+        let split_flags = |flags: &str| {
+            flags
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|flag| !flag.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        };
+        if RE_TABLE_HEADER.is_match(line) {
+            true
+        } else if let Some(entry_cap) = RE_TABLE_ENTRY.captures(line) {
+            let flags = entry_cap.name("flags").map_or("", |m| m.as_str());
+            // llvm-objdump lists a nameless null section first
+            if !entry_cap["name"].is_empty() {
+                table.push((entry_cap["name"].to_string(), split_flags(flags)));
+            }
+            true
+        } else if let Some(flags_cap) = RE_TABLE_FLAGS.captures(line) {
+            match table.last_mut() {
+                Some((_, flags)) => {
+                    flags.extend(split_flags(&flags_cap["flags"]));
+                    true
+                }
+                None => false,
+            }
+        } else {
+            false
+        }
+    }
+
+    fn process_other_line(
+        &mut self,
+        line: &str,
+        regexes: &SyntaxRegexes,
+        options: &ParseOptions,
+    ) -> Result<LineKind, DisasmError> {
         lazy_static! {
             static ref RE_SECTION: Regex =
-                Regex::new(r"^Disassembly of section (?P<sec_name>.[[:alnum:].]+):$").unwrap();
-            static ref RE_SYMBOL: Regex = Regex::new(r"^(?P<sym_name><.+>):$").unwrap();
-            static ref RE_INSTRUCTION: Regex = Regex::new(
+                Regex::new(r"^Disassembly of section (?P<sec_name>.[[:alnum:]._,@-]+):$").unwrap();
+            static ref RE_SYMBOL: Regex =
+                Regex::new(r"^((?P<sym_addr>[[:xdigit:]]{1,16})[[:space:]]+)?(?P<sym_name><.+>):$")
+                    .unwrap();
+            static ref RE_RELOCATION: Regex = Regex::new(
                 r"(?x)^
-                    [[:space:]]
-                    (?P<opcode>  [[[:lower:]][[:digit:]][[:space:]]]*)
-                    (?P<operands>[[:space:]]+[^[[:space:]]]+)??
-                    ([[:space:]]+\#(?P<comment>.*))??
+                    [[:space:]]+
+                    ([[:xdigit:]]{1,16}:[[:space:]]+)?
+                    (?P<reloc_type>R_[[:alnum:]_]+)
+                    [[:space:]]+
+                    (?P<reloc_target>.+?)
                     [[:space:]]*
                     $"
             )
             .unwrap();
+            static ref RE_CONTINUATION: Regex =
+                Regex::new(r"^\t\t+(?P<continuation>[^[:space:]].*?)[[:space:]]*$").unwrap();
+            static ref RE_RAW_BYTES: Regex = Regex::new(
+                r"(?x)^
+                    (?P<prefix>   [[:space:]]*([[:xdigit:]]{1,16}:)?)
+                    [[:space:]]+
+                    (?P<raw_bytes>[[:xdigit:]]{2}(\x20[[:xdigit:]]{2})*)
+                    \x20*
+                    (\t|$)"
+            )
+            .unwrap();
         }
-
         if let Some(sec_name) = RE_SECTION
             .captures(line)
             .and_then(|cap| cap.name("sec_name").map(|sec| sec.as_str()))
         {
-            self.add_section(Section::new(sec_name));
-            Ok(())
-        } else if let Some(sym_name) = RE_SYMBOL
+            self.open_section(Section::new(sec_name), options.merge_duplicate_sections);
+            Ok(LineKind::Section)
+        } else if let Some(sym_cap) = RE_SYMBOL.captures(line) {
+            let sym_name = sym_cap.name("sym_name").map_or("", |m| m.as_str()).trim();
+            if !Symbol::is_well_formed_name(sym_name) {
+                return Err(DisasmError::MalformedSymbolName(sym_name.to_string()));
+            }
+            let mut symbol = match options.demangle {
+                Some(demangler) => Symbol::new(&demangle::demangle(sym_name, demangler)),
+                None => Symbol::new(sym_name),
+            };
+            if let Some(sym_addr) = sym_cap.name("sym_addr") {
+                let address = u64::from_str_radix(sym_addr.as_str(), 16)?;
+                symbol = symbol.with_address(address);
+            }
+            self.add_symbol(symbol).map(|_| LineKind::Symbol)
+        } else if line.trim() == "..." {
+            self.add_instruction(Instruction::new_skip())
+                .map(|_| LineKind::Other)
+        } else if line.trim() == "(No symbols)" {
+            // Some objdump versions mark sections without symbols, leave the section empty
+            Ok(LineKind::Other)
+        } else if let Some(reloc_cap) = RE_RELOCATION.captures(line) {
+            let reloc = format!(
+                "{} {}",
+                &reloc_cap["reloc_type"], &reloc_cap["reloc_target"]
+            );
+            self.last_instruction_mut()
+                .ok_or(DisasmError::RelocationBeforeInstruction)?
+                .add_reloc(&reloc);
+            Ok(LineKind::Other)
+        } else if let Some(comment_cap) = regexes
+            .llvm_comment
+            .captures(line)
+            .filter(|_| options.backend == Backend::Llvm)
+        {
+            // llvm-objdump prints additional comments on their own line
+            let instruction = self
+                .last_instruction_mut()
+                .ok_or(DisasmError::CommentBeforeInstruction)?;
+            if !options.strip_comments {
+                instruction.append_comment(&comment_cap["comment"]);
+            }
+            Ok(LineKind::Other)
+        } else if let Some((cont_cap, instruction)) = RE_CONTINUATION
             .captures(line)
-            .and_then(|cap| cap.name("sym_name").map(|sym| sym.as_str()))
+            .zip(self.last_instruction_mut())
+            .filter(|(_, instruction)| instruction.operands().ends_with(','))
         {
-            self.add_symbol(Symbol::new(sym_name.trim()))
-        } else if let Some(ins_cap) = RE_INSTRUCTION.captures(line) {
-            let opcode = ins_cap.name("opcode").map_or("", |m| m.as_str()).trim();
-            let operands = ins_cap.name("operands").map_or("", |m| m.as_str()).trim();
-            let comment = ins_cap.name("comment").map_or("", |m| m.as_str()).trim();
-            self.add_instruction(Instruction::new(opcode, operands, comment))
+            // Long instructions may be wrapped after a comma, on a line indented deeper than the instructions. Other
+            // lines indented by multiple tabs are parsed as instructions.
+            instruction.append_operands(&cont_cap["continuation"]);
+            Ok(LineKind::Other)
+        } else if let Some(raw_cap) = RE_RAW_BYTES.captures(line).filter(|_| options.raw_bytes) {
+            let raw_bytes = raw_cap["raw_bytes"]
+                .split(' ')
+                .map(|byte| u8::from_str_radix(byte, 16))
+                .collect::<Result<Vec<_>, _>>()?;
+            let leftover_line = &line[raw_cap.get(0).map_or(0, |m| m.end())..];
+            if leftover_line.trim().is_empty() {
+                // Objdump wraps the bytes of long instructions on the following lines
+                self.last_instruction_mut()
+                    .ok_or(DisasmError::RawBytesBeforeInstruction)?
+                    .extend_raw_bytes(&raw_bytes);
+                Ok(LineKind::Other)
+            } else {
+                let ins_line = format!("{}\t{}", &raw_cap["prefix"], leftover_line);
+                match Disasm::parse_instruction(&ins_line, &regexes.instruction, options) {
+                    Some(instruction) => self
+                        .add_instruction(instruction?.with_raw_bytes(raw_bytes))
+                        .map(|_| LineKind::Instruction),
+                    None => Err(DisasmError::UnrecognizedLine(line.to_string())),
+                }
+            }
+        } else if let Some(instruction) =
+            Disasm::parse_instruction(line, &regexes.instruction, options)
+        {
+            self.add_instruction(instruction?)
+                .map(|_| LineKind::Instruction)
         } else {
-            Err(format!(
-                "Unrecognized format for the following line: '{line}'"
-            ))
+            Err(DisasmError::UnrecognizedLine(line.to_string()))
         }
     }
 
-    fn add_section(&mut self, section: Section) {
-        self.sections.push(section);
+    fn parse_instruction(
+        line: &str,
+        re_instruction: &Regex,
+        options: &ParseOptions,
+    ) -> Option<Result<Instruction, DisasmError>> {
+        lazy_static! {
+            static ref RE_DIRECTIVE: Regex = Regex::new(
+                r"(?x)^
+                    ([[:space:]]*(?P<address>[[:xdigit:]]{1,16}):)?
+                    [[:space:]]+
+                    (?P<opcode>  \.[[:alpha:]][[:alnum:]_]*)
+                    ([[:space:]]+(?P<operands>.*?))?
+                    [[:space:]]*
+                    $"
+            )
+            .unwrap();
+        }
+        if let Some(dir_cap) = RE_DIRECTIVE.captures(line) {
+            let directive = Instruction::new_directive(
+                &dir_cap["opcode"],
+                dir_cap.name("operands").map_or("", |m| m.as_str()),
+            );
+            return Some(match dir_cap.name("address") {
+                Some(dir_addr) => u64::from_str_radix(dir_addr.as_str(), 16)
+                    .map(|address| directive.with_address(address))
+                    .map_err(DisasmError::from),
+                None => Ok(directive),
+            });
+        }
+        let ins_cap = re_instruction.captures(line)?;
+        // Prefixes may be separated from the opcode by multiple spaces or tabs
+        let opcode = ins_cap.name("opcode").map_or("", |m| m.as_str());
+        let opcode = if options.preserve_spacing {
+            opcode.to_string()
+        } else {
+            opcode.split_whitespace().collect::<Vec<_>>().join(" ")
+        };
+        let operands = ins_cap.name("operands").map_or("", |m| m.as_str()).trim();
+        let comment = match ins_cap.name("comment") {
+            Some(comment) if !options.strip_comments => comment.as_str().trim(),
+            _ => "",
+        };
+        let instruction = Instruction::new(&opcode, operands, comment);
+        match ins_cap.name("address") {
+            Some(ins_addr) => Some(
+                u64::from_str_radix(ins_addr.as_str(), 16)
+                    .map(|address| instruction.with_address(address))
+                    .map_err(DisasmError::from),
+            ),
+            None => Some(Ok(instruction)),
+        }
     }
 
-    fn add_symbol(&mut self, symbol: Symbol) -> Result<(), String> {
-        self.sections
-            .last_mut()
-            .ok_or("Attempted to add a symbol without first defining a section")?
+    /// Append `section`, the following symbols are added to it.
+    pub fn add_section(&mut self, section: Section) {
+        self.push_section(section);
+    }
+
+    /// Start a section met while parsing, going back to the existing one in place when merging duplicates.
+    fn open_section(&mut self, section: Section, merge_duplicates: bool) {
+        let existing = self
+            .section_index
+            .get(section.get_name())
+            .copied()
+            .filter(|_| merge_duplicates);
+        match existing {
+            Some(idx) => self.current_section = Some(idx),
+            None => self.push_section(section),
+        }
+    }
+
+    /// Append `symbol` to the current section, the last one added.
+    pub fn add_symbol(&mut self, symbol: Symbol) -> Result<(), DisasmError> {
+        self.current_section_mut()
+            .ok_or(DisasmError::SymbolBeforeSection)?
             .add_symbol(symbol);
         Ok(())
     }
 
-    fn add_instruction(&mut self, instruction: Instruction) -> Result<(), String> {
-        self.sections
-            .last_mut()
-            .ok_or("Attempted to add an instruction without first defining a section")?
+    /// Append `instruction` to the last symbol of the current section.
+    pub fn add_instruction(&mut self, instruction: Instruction) -> Result<(), DisasmError> {
+        self.current_section_mut()
+            .ok_or(DisasmError::InstructionBeforeSection)?
             .add_instruction(instruction)
     }
 
-    fn sort_sections(&mut self) {
+    fn last_instruction_mut(&mut self) -> Option<&mut Instruction> {
+        self.current_section_mut()?.last_instruction_mut()
+    }
+
+    /// Sort the sections by name, and the symbols of each section by `sort_by`.
+    pub fn sort(&mut self, sort_by: SortKey) {
+        self.sort_with(sort_by, false);
+    }
+
+    /// Sort like [`Disasm::sort`], optionally ignoring the case of the names.
+    pub fn sort_with(&mut self, sort_by: SortKey, case_insensitive: bool) {
+        self.sort_by(
+            |a, b| section::compare_names(a.get_name(), b.get_name(), case_insensitive),
+            |a, b| section::compare_symbols(a, b, sort_by, case_insensitive),
+        );
+    }
+
+    /// Sort the sections and the symbols by name.
+    pub fn sort_by_name(&mut self) {
+        self.sort(SortKey::Name);
+    }
+
+    /// Sort the sections with the comparator `compare_sections`, and the symbols of each section with
+    /// `compare_symbols`. Both sorts are stable.
+    ///
+    /// ```
+    /// use disasm_util::Disasm;
+    ///
+    /// let text = "file.o:     file format elf64-x86-64\n\
+    ///             Disassembly of section .init:\n\
+    ///             <_init>:\n\
+    ///             Disassembly of section .text:\n\
+    ///             <main>:\n\
+    ///             <_start>:\n";
+    /// let mut disasm = Disasm::try_from(text.to_string()).unwrap();
+    /// // Sections with the most symbols first, symbols in reverse alphabetical order
+    /// disasm.sort_by(|a, b| b.len().cmp(&a.len()), |a, b| b.get_name().cmp(a.get_name()));
+    /// let order: Vec<_> = disasm
+    ///     .sections()
+    ///     .flat_map(|section| section.symbols())
+    ///     .map(|symbol| symbol.get_name().as_str())
+    ///     .collect();
+    /// assert_eq!(order, ["<main>", "<_start>", "<_init>"]);
+    /// ```
+    pub fn sort_by<F, G>(&mut self, compare_sections: F, mut compare_symbols: G)
+    where
+        F: FnMut(&Section, &Section) -> Ordering,
+        G: FnMut(&Symbol, &Symbol) -> Ordering,
+    {
         for section in &mut self.sections {
-            section.sort_symbols();
+            section.sort_symbols_with(&mut compare_symbols);
         }
-        self.sections.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        self.sections.sort_by(compare_sections);
+        self.reindex();
     }
-}
 
-impl TryFrom<String> for Disasm {
-    type Error = String;
+    /// Pretty print the disassembly according to `options`.
+    pub fn to_string_with(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_with(&mut out, options)
+            .expect("writing to a String can't fail");
+        out
+    }
 
-    fn try_from(text: String) -> Result<Self, Self::Error> {
-        let mut disasm = Disasm {
-            file_name: String::from(""),
-            file_format: String::from(""),
-            sections: Vec::new(),
-        };
-        // Filter out empty lines
-        let mut lines_iter = text.lines().filter(|line| !line.trim().is_empty());
-        // Process the first line
-        let line = lines_iter
-            .next()
-            .ok_or("Error, the file does not contain any text".to_string())?;
-        disasm.process_first_line(line)?;
-        // Process all other lines
-        for line in lines_iter {
-            disasm.process_other_line(line)?;
+    /// Render the disassembly as Markdown, with a heading for each section and symbol.
+    ///
+    /// The instructions of each symbol, formatted according to `options`, are placed in a fenced code block.
+    pub fn to_markdown(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_markdown(&mut out, options)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_markdown<W: fmt::Write>(&self, out: &mut W, options: &FormatOptions) -> fmt::Result {
+        for section in &self.sections {
+            write!(out, "## {}\n\n", markdown::escape(section.get_name()))?;
+            for symbol in section.symbols() {
+                write!(out, "### {}\n\n", markdown::escape(symbol.get_name()))?;
+                if !symbol.is_empty() {
+                    let mut code = String::new();
+                    for instruction in symbol.instructions() {
+                        instruction.write_with(&mut code, options)?;
+                    }
+                    writeln!(out, "{}", markdown::code_block(&code))?;
+                }
+            }
         }
-        // Sort the stored data
-        disasm.sort_sections();
-        Ok(disasm)
+        Ok(())
+    }
+
+    /// Render the disassembly with one `section/symbol: instruction` line per instruction, formatted according to
+    /// `options`.
+    ///
+    /// Symbols without instructions are printed as a `section/symbol:` line and sections without symbols as a
+    /// `section:` line.
+    pub fn to_flat_string(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_flat(&mut out, options)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_flat<W: fmt::Write>(&self, out: &mut W, options: &FormatOptions) -> fmt::Result {
+        for section in &self.sections {
+            if section.is_empty() {
+                writeln!(out, "{}:", section.get_name())?;
+            }
+            for symbol in section.symbols() {
+                let prefix = format!("{}/{}:", section.get_name(), symbol.get_name());
+                if symbol.is_empty() {
+                    writeln!(out, "{prefix}")?;
+                }
+                for instruction in symbol.instructions() {
+                    write!(out, "{prefix} ")?;
+                    instruction.write_with(out, options)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write one CSV row per instruction, with a header row, into `writer`.
+    ///
+    /// The columns are section, symbol, address, opcode, operands and comment, missing values are left empty.
+    /// The '...' lines are not included.
+    pub fn to_csv<W: io::Write>(&self, writer: W) -> Result<(), DisasmError> {
+        let mut csv_writer = csv::Writer::from_writer(writer);
+        csv_writer.write_record([
+            "section", "symbol", "address", "opcode", "operands", "comment",
+        ])?;
+        for section in &self.sections {
+            for symbol in section.symbols() {
+                for instruction in symbol
+                    .instructions()
+                    .filter(|instruction| instruction.kind() != InstructionKind::Skip)
+                {
+                    let address = instruction
+                        .get_address()
+                        .map_or(String::new(), |address| format!("{address:x}"));
+                    csv_writer.write_record([
+                        section.get_name(),
+                        symbol.get_name(),
+                        &address,
+                        instruction.opcode(),
+                        instruction.operands(),
+                        instruction.comment(),
+                    ])?;
+                }
+            }
+        }
+        csv_writer.flush()?;
+        Ok(())
+    }
+
+    /// Pretty print the disassembly according to `options` into `out`, coloring sections and symbols.
+    ///
+    /// The colors are only emitted if `out` supports them, otherwise the output matches [`Disasm::to_string_with`].
+    pub fn write_colored<W: WriteColor>(&self, out: W, options: &FormatOptions) -> io::Result<()> {
+        let mut writer = ColorWriter::new(out);
+        let result = self.write_with(&mut writer, options);
+        writer.into_result(result)
+    }
+
+    /// Pretty print the symbols grouped by name as in [`Disasm::regroup_by_symbol`], according to `options`.
+    ///
+    /// Each merged symbol is printed as in [`Disasm::to_string_with`].
+    pub fn to_string_by_symbol(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_by_symbol(&mut out, options)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Pretty print the symbols grouped by name into `out`, coloring their names.
+    ///
+    /// The colors are only emitted if `out` supports them, otherwise the output matches
+    /// [`Disasm::to_string_by_symbol`].
+    pub fn write_colored_by_symbol<W: WriteColor>(
+        &self,
+        out: W,
+        options: &FormatOptions,
+    ) -> io::Result<()> {
+        let mut writer = ColorWriter::new(out);
+        let result = self.write_by_symbol(&mut writer, options);
+        writer.into_result(result)
+    }
+
+    fn write_by_symbol<W: StyledWrite>(&self, out: &mut W, options: &FormatOptions) -> fmt::Result {
+        for (name, instructions) in self.regroup_by_symbol() {
+            symbol::write_symbol(out, &name, &instructions, options, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Print the disassembly in the layout of GNU objdump, reproducing its input when parsed keeping the order.
+    ///
+    /// Only what the parser retains is printed, so operands, addresses and raw bytes need to be parsed to appear in the
+    /// output, and the offsets of the relocations are always left out.
+    ///
+    /// ```
+    /// use disasm_util::{Disasm, ParseOptions};
+    ///
+    /// let text = "\nfile.o:     file format elf64-x86-64\n\n\n\
+    ///             Disassembly of section .text:\n\n\
+    ///             <main>:\n\
+    ///             \tmov    %rsp,%rbp\n\
+    ///             \tret\n";
+    /// let options = ParseOptions {
+    ///     keep_order: true,
+    ///     ..Default::default()
+    /// };
+    /// let disasm = Disasm::from_lines(text.lines(), &options).unwrap();
+    /// assert_eq!(disasm.to_objdump_string(), text);
+    /// ```
+    pub fn to_objdump_string(&self) -> String {
+        let mut out = String::new();
+        self.write_objdump(&mut out)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    fn write_objdump<W: fmt::Write>(&self, out: &mut W) -> fmt::Result {
+        let address_digits = self
+            .parsed_file_format()
+            .bitness()
+            .map_or(16, |bits| bits as usize / 4);
+        writeln!(
+            out,
+            "\n{}:     file format {}\n",
+            self.file_name, self.file_format
+        )?;
+        for section in &self.sections {
+            writeln!(out, "\nDisassembly of section {}:", section.get_name())?;
+            let instruction_width = objdump_address_width(section, address_digits);
+            for symbol in section.symbols() {
+                out.write_char('\n')?;
+                if let Some(address) = symbol.get_address() {
+                    write!(out, "{address:0address_digits$x} ")?;
+                }
+                writeln!(out, "{}:", symbol.get_name())?;
+                for instruction in symbol.instructions() {
+                    instruction.write_objdump(out, instruction_width)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pretty print the disassembly according to `options` into `out`, without building the whole output in memory.
+    ///
+    /// The output matches [`Disasm::to_string_with`].
+    pub fn write_to<W: io::Write>(&self, out: &mut W, options: &FormatOptions) -> io::Result<()> {
+        self.write_colored(NoColor::new(out), options)
+    }
+
+    fn write_with<W: StyledWrite>(&self, out: &mut W, options: &FormatOptions) -> fmt::Result {
+        for section in &self.sections {
+            section.write_with(out, options, 0)?;
+        }
+        Ok(())
     }
 }
 
-impl fmt::Display for Disasm {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let joined = self
-            .sections
-            .iter()
-            .map(|sec| sec.to_string())
-            .collect::<Vec<_>>()
-            .join("");
-        write!(f, "{}", joined)
+/// Regexes of the lines whose layout depends on the objdump syntax and on the comment delimiter.
+#[derive(Clone)]
+struct SyntaxRegexes {
+    instruction: Regex,
+    llvm_comment: Regex,
+}
+
+impl SyntaxRegexes {
+    /// Build the regexes for the objdump `backend` and syntax, with comments introduced by `comment_char`.
+    fn new(backend: Backend, intel: bool, comment_char: char) -> Self {
+        // A hex escape matches the delimiter both inside and outside character classes, even in verbose mode
+        let comment = format!(r"\x{{{:x}}}", u32::from(comment_char));
+        // llvm-objdump separates opcode and operands with a tab for both syntaxes
+        let instruction = match (backend, intel) {
+            (Backend::Llvm, _) => format!(
+                r"(?x)^
+                    ([[:space:]]*(?P<address>[[:xdigit:]]{{1,16}}):)?
+                    [[:space:]]+
+                    (?P<opcode>  (({INSTRUCTION_PREFIXES})[[:space:]]+)*
+                                 [[:alnum:].]+)
+                    (\t(?P<operands>[^{comment}]*?))??
+                    ([[:space:]]+{comment}(?P<comment>.*))??
+                    [[:space:]]*
+                    $"
+            ),
+            (Backend::Gnu, true) => format!(
+                r"(?x)^
+                    ([[:space:]]*(?P<address>[[:xdigit:]]{{1,16}}):)?
+                    [[:space:]]+
+                    (?P<opcode>  (({INSTRUCTION_PREFIXES})[[:space:]])*
+                                 [[:lower:][:digit:]]+)
+                    ([[:space:]]+(?P<operands>[^{comment};]*?))??
+                    ([[:space:]]+[{comment};](?P<comment>.*))??
+                    [[:space:]]*
+                    $"
+            ),
+            (Backend::Gnu, false) => format!(
+                r"(?x)^
+                    ([[:space:]]*(?P<address>[[:xdigit:]]{{1,16}}):)?
+                    [[:space:]]+
+                    (?P<opcode>  ((({INSTRUCTION_PREFIXES})[[:space:]]+)*
+                                  [[:alnum:].]+)?)
+                    (?P<operands>[[:space:]]+(<.*?>|[^[[:space:]]]+([[:space:]]+<.*?>)?))??
+                    ([[:space:]]+{comment}(?P<comment>.*))??
+                    [[:space:]]*
+                    $"
+            ),
+        };
+        let llvm_comment =
+            format!(r"^[[:space:]]+{comment}[[:space:]]*(?P<comment>.*?)[[:space:]]*$");
+        SyntaxRegexes {
+            instruction: Regex::new(&instruction).expect("the instruction regex is valid"),
+            llvm_comment: Regex::new(&llvm_comment).expect("the comment regex is valid"),
+        }
+    }
+
+    /// Regexes for `options`, built at runtime when the comments are not introduced by the default `#` character.
+    fn for_options(options: &ParseOptions) -> Cow<'static, SyntaxRegexes> {
+        lazy_static! {
+            static ref RE_GNU: SyntaxRegexes = SyntaxRegexes::new(Backend::Gnu, false, '#');
+            static ref RE_GNU_INTEL: SyntaxRegexes = SyntaxRegexes::new(Backend::Gnu, true, '#');
+            static ref RE_LLVM: SyntaxRegexes = SyntaxRegexes::new(Backend::Llvm, false, '#');
+        }
+        match options
+            .comment_char
+            .filter(|comment_char| *comment_char != '#')
+        {
+            Some(comment_char) => Cow::Owned(SyntaxRegexes::new(
+                options.backend,
+                options.intel,
+                comment_char,
+            )),
+            None => Cow::Borrowed(match (options.backend, options.intel) {
+                (Backend::Llvm, _) => &RE_LLVM,
+                (Backend::Gnu, true) => &RE_GNU_INTEL,
+                (Backend::Gnu, false) => &RE_GNU,
+            }),
+        }
+    }
+}
+
+/// Width of the instruction addresses of `section` in the objdump layout.
+///
+/// Like objdump, the leading zeros of the highest address, printed with `digits` digits, are dropped in groups of four
+/// while keeping at least one.
+fn objdump_address_width(section: &Section, digits: usize) -> usize {
+    let highest = section
+        .symbols()
+        .flat_map(|symbol| symbol.instructions())
+        .filter_map(|instruction| {
+            let size = instruction.get_raw_bytes().map_or(0, <[u8]>::len);
+            Some(instruction.get_address()? + size as u64)
+        })
+        .max()
+        .unwrap_or_default();
+    let leading_zeros = digits.saturating_sub(format!("{highest:x}").len());
+    match leading_zeros {
+        0 => digits,
+        zeros => digits - ((zeros - 1) & !3),
+    }
+}
+
+/// Parse a disassembly from a string slice with the default parsing options.
+///
+/// ```
+/// use disasm_util::Disasm;
+///
+/// let disasm = "file.o:     file format elf64-x86-64\n\
+///               Disassembly of section .text:\n\
+///               <main>:\n\
+///               \tret\n"
+///     .parse::<Disasm>()
+///     .unwrap();
+/// assert_eq!(disasm.summary().instructions, 1);
+/// ```
+impl FromStr for Disasm {
+    type Err = DisasmError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Disasm::from_lines(text.lines(), &ParseOptions::default())
+    }
+}
+
+/// Parse the whole output of an objdump disassembly with the default parsing options.
+impl TryFrom<String> for Disasm {
+    type Error = DisasmError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl fmt::Display for Disasm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_with(f, &FormatOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::{formatdoc, indoc};
+
+    #[test]
+    fn file_name_path_separators_ok() {
+        for (first_line, file_name) in [
+            ("folder\\file:     file format elf64-x86-64", "folder\\file"),
+            ("folder/file:     file format elf64-x86-64", "folder/file"),
+            (
+                "C:\\folder\\file:     file format pe-x86-64",
+                "C:\\folder\\file",
+            ),
+        ] {
+            let mut disasm = Disasm::from_lines([first_line], &ParseOptions::default()).unwrap();
+            assert_eq!(disasm.get_file_name(), file_name);
+            assert_eq!(disasm.file_base_name(), "file");
+
+            disasm.normalize_path_separator('/');
+            assert_eq!(disasm.get_file_name(), &file_name.replace('\\', "/"));
+        }
+    }
+
+    #[test]
+    fn try_from_simple_corect_file_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+
+
+            Disassembly of section sec1:
+
+            <sym1>:
+                opc1
+                opc2    %opr1,%opr2
+                opc3    %opr3                   # comment1
+
+            <sym2>:
+                opc4   %opr4  # comment2
+
+            Disassembly of section sec2:
+
+            <sym3>:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<sym1>"));
+        let _ = sec1.add_instruction(Instruction::new("opc1", "", ""));
+        let _ = sec1.add_instruction(Instruction::new("opc2", "%opr1,%opr2", ""));
+        let _ = sec1.add_instruction(Instruction::new("opc3", "%opr3", "comment1"));
+        sec1.add_symbol(Symbol::new("<sym2>"));
+        let _ = sec1.add_instruction(Instruction::new("opc4", "%opr4", "comment2"));
+        let mut sec2 = Section::new("sec2");
+        sec2.add_symbol(Symbol::new("<sym3>"));
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [sec1, sec2])
+        )
+    }
+
+    #[test]
+    fn from_reader_ok() {
+        let text = indoc! {"
+            folder\\file:     file format some_format
+
+            Disassembly of section sec1:
+            <sym1>:
+            \tnop
+        "};
+        let from_reader = Disasm::from_reader(text.as_bytes(), &ParseOptions::default());
+        let from_lines = Disasm::from_lines(text.lines(), &ParseOptions::default());
+        assert_eq!(from_reader.unwrap(), from_lines.unwrap());
+    }
+
+    #[test]
+    fn from_reader_invalid_utf8_fails() {
+        let text: &[u8] = b"folder\\file:     file format some_format\n\xff\xfe\n";
+        let result = Disasm::from_reader(text, &ParseOptions::default());
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err("Error on line 2: stream did not contain valid UTF-8".to_string())
+        );
+    }
+
+    #[test]
+    fn try_from_empty_string_fails() {
+        let result = Disasm::try_from("".to_string());
+        assert!(matches!(result, Err(DisasmError::EmptyInput)));
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err("Error, the file does not contain any text".to_string())
+        )
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_first_line_fails() {
+        let result = Disasm::try_from("New line with incorrect formatting".to_string());
+        match &result {
+            Err(DisasmError::Line { line_no, error }) => {
+                assert_eq!(*line_no, 1);
+                assert!(matches!(**error, DisasmError::BadFirstLine));
+            }
+            _ => panic!("unexpected result {result:?}"),
+        }
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err("Error on line 1: Incorrect format for the first line".to_string())
+        )
+    }
+
+    #[test]
+    fn try_from_banner_before_first_line_ok() {
+        let lines = indoc! {"
+            In archive libfoo.a:
+
+            SYMBOL TABLE:
+            architecture: i386:x86-64, flags 0x00000011:
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+        "}
+        .to_string();
+
+        let disasm = Disasm::try_from(lines).unwrap();
+
+        assert_eq!(disasm.get_file_name(), "folder\\file");
+        assert_eq!(disasm.get_file_format(), "some_format");
+        assert_eq!(disasm.len(), 1);
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_section_name_fixed_part_fails() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            gibberish of section sec1:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 2: Unrecognized format for the following line: 'gibberish of section sec1:'"
+                    .to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_section_name_strange_sec_name_fails() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec%1:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 2: Unrecognized format for the following line: 'Disassembly of section sec%1:'"
+                    .to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_section_name_trailing_gibberish_fails() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:gibberish:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 2: Unrecognized format for the following line: 'Disassembly of section sec1:gibberish:'"
+                    .to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn try_from_special_characters_section_names_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section __TEXT,__text:
+            Disassembly of section .text.unlikely:
+            Disassembly of section .text-hot@1:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with(
+                "folder\\file",
+                "some_format",
+                [
+                    Section::new(".text-hot@1"),
+                    Section::new(".text.unlikely"),
+                    Section::new("__TEXT,__text"),
+                ]
+            )
+        )
+    }
+
+    #[test]
+    fn try_from_symbols_with_and_without_address_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            0000000000001040 <sym1>:
+            <sym2>:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<sym1>").with_address(0x1040));
+        sec1.add_symbol(Symbol::new("<sym2>"));
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [sec1])
+        )
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_symbol_missing_start_lt_fails() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section sec1:
+            sym1>:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 3: Unrecognized format for the following line: 'sym1>:'".to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_symbol_missing_end_fails() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err("Error on line 3: Unrecognized format for the following line: '<sym1'".to_string())
+        )
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_instruction_missing_leading_space_fails() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            opc1 opc2    %opr1,%opr2          # comment1
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err("Error on line 4: Unrecognized format for the following line: 'opc1 opc2    %opr1,%opr2          # comment1'".to_string())
+        )
+    }
+
+    #[test]
+    fn try_from_incorrectly_formatted_instruction_incorrect_opcode_format_fails() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            	%opc1 opc2    %opr1,%opr2          # comment1
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err("Error on line 4: Unrecognized format for the following line: '	%opc1 opc2    %opr1,%opr2          # comment1'".to_string())
+        )
+    }
+
+    #[test]
+    fn from_lines_lenient_skips_unrecognized_lines_ok() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section sec1:
+            some banner
+            <sym1>:
+            	opc1
+            Opc2 gibberish
+        "};
+
+        let result = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                lenient: true,
+                ..Default::default()
+            },
+        );
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<sym1>"));
+        let _ = sec1.add_instruction(Instruction::new("opc1", "", ""));
+
+        let mut expected = disasm_with("folder\\file", "some_format", [sec1]);
+        expected.skipped_lines = Vec::from([
+            (3, "some banner".to_string()),
+            (6, "Opc2 gibberish".to_string()),
+        ]);
+        assert_eq!(result.unwrap(), expected)
+    }
+
+    #[test]
+    fn from_lines_malformed_symbol_name_fails() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <foo<bar>:
+        "};
+
+        let result = Disasm::from_lines(lines.lines(), &ParseOptions::default());
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 3: Mismatched angle brackets in the symbol name '<foo<bar>'"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn from_lines_leading_whitespace_ok() {
+        let parse = |lead: &str, options: &ParseOptions| {
+            let lines = format!(
+                "file.o:     file format elf64-x86-64\n\
+                 Disassembly of section .text:\n\
+                 <sym1>:\n\
+                 {lead}mov\t%rsp,%rbp\n\
+                 {lead}ret\n\
+                 \x20 10:{lead}nop\n"
+            );
+            Disasm::from_lines(lines.lines(), options).unwrap()
+        };
+        for options in [
+            ParseOptions::default(),
+            ParseOptions {
+                intel: true,
+                ..Default::default()
+            },
+            ParseOptions {
+                backend: Backend::Llvm,
+                ..Default::default()
+            },
+        ] {
+            let tab_led = parse("\t", &options);
+            assert_eq!(tab_led.summary().instructions, 3);
+            assert_eq!(parse("    ", &options), tab_led);
+            assert_eq!(parse(" \t", &options), tab_led);
+            assert_eq!(parse("\t  ", &options), tab_led);
+        }
+    }
+
+    #[test]
+    fn from_lines_continuation_line_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <sym1>:
+            \tvpternlogd $0xff,%zmm0,%zmm0,
+            \t\t\t%zmm0{%k1}
+            \tret
+            \t\tnop
+        "};
+
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+        let instructions = disasm.sections[0]
+            .symbols()
+            .flat_map(|symbol| symbol.instructions())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            [
+                &Instruction::new("vpternlogd", "$0xff,%zmm0,%zmm0, %zmm0{%k1}", ""),
+                &Instruction::new("ret", "", ""),
+                &Instruction::new("nop", "", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn from_lines_wide_long_line_ok() {
+        let operands = format!("$0x{},%zmm0{{%k1}}", "f".repeat(500));
+        let comment = format!("<{}>", "a".repeat(500));
+        let lines = [
+            "file.o:     file format elf64-x86-64".to_string(),
+            "Disassembly of section .text:".to_string(),
+            "<sym1>:".to_string(),
+            format!(
+                "\t62 f3 7d 49 25 c0 ff 62 f3 7d 49 25 c0 ff 00 \tvpternlogd {operands}    # {comment}"
+            ),
+        ];
+        let options = ParseOptions {
+            raw_bytes: true,
+            ..Default::default()
+        };
+
+        let disasm = Disasm::from_lines(lines.iter().map(String::as_str), &options).unwrap();
+
+        let instructions = disasm.sections[0]
+            .symbols()
+            .flat_map(|symbol| symbol.instructions())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            [
+                &Instruction::new("vpternlogd", &operands, &comment).with_raw_bytes(vec![
+                    0x62, 0xf3, 0x7d, 0x49, 0x25, 0xc0, 0xff, 0x62, 0xf3, 0x7d, 0x49, 0x25, 0xc0,
+                    0xff, 0x00
+                ])
+            ]
+        );
+    }
+
+    #[test]
+    fn from_lines_comment_char_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <sym1>:
+            \tmov    0x8(%rip),%rax ; 1040 <counter>
+        "};
+        let options = ParseOptions {
+            comment_char: Some(';'),
+            ..Default::default()
+        };
+
+        assert!(Disasm::from_lines(lines.lines(), &ParseOptions::default()).is_err());
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+        let instruction = disasm.sections[0]
+            .symbols()
+            .flat_map(|symbol| symbol.instructions())
+            .next()
+            .unwrap();
+        assert_eq!(
+            instruction,
+            &Instruction::new("mov", "0x8(%rip),%rax", "1040 <counter>")
+        );
+    }
+
+    #[test]
+    fn from_lines_comment_char_llvm_ok() {
+        let lines = indoc! {"
+            file.o:\tfile format elf64-x86-64
+            Disassembly of section .text:
+            <sym1>:
+                                       \tmovq\t128471(%rip), %xmm0     ; xmm0 = mem[0],zero
+                                                                ; 0x245e0 <optarg>
+        "};
+        let options = ParseOptions {
+            backend: Backend::Llvm,
+            comment_char: Some(';'),
+            ..Default::default()
+        };
+
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        let instruction = disasm.sections[0]
+            .symbols()
+            .flat_map(|symbol| symbol.instructions())
+            .next()
+            .unwrap();
+        assert_eq!(
+            instruction,
+            &Instruction::new(
+                "movq",
+                "128471(%rip), %xmm0",
+                "xmm0 = mem[0],zero; 0x245e0 <optarg>"
+            )
+        );
+    }
+
+    #[test]
+    fn from_lines_comment_char_class_metacharacter_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <sym1>:
+            \tmov    rax,QWORD PTR [rip+0x8] ^ 1040 <counter>
+        "};
+        let options = ParseOptions {
+            intel: true,
+            comment_char: Some('^'),
+            ..Default::default()
+        };
+
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        let instruction = disasm.sections[0]
+            .symbols()
+            .flat_map(|symbol| symbol.instructions())
+            .next()
+            .unwrap();
+        assert_eq!(
+            instruction,
+            &Instruction::new("mov", "rax,QWORD PTR [rip+0x8]", "1040 <counter>")
+        );
+    }
+
+    #[test]
+    fn from_lines_preserve_spacing_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <sym1>:
+            \tbnd jmp <_init+0x20>
+            \trep  stos %rax,%es:(%rdi)
+        "};
+        let opcodes = |options: &ParseOptions| {
+            let disasm = Disasm::from_lines(lines.lines(), options).unwrap();
+            disasm.sections[0]
+                .symbols()
+                .flat_map(|symbol| symbol.instructions())
+                .map(|instruction| instruction.opcode().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(opcodes(&ParseOptions::default()), ["bnd jmp", "rep stos"]);
+        assert_eq!(
+            opcodes(&ParseOptions {
+                preserve_spacing: true,
+                ..Default::default()
+            }),
+            ["bnd jmp", "rep  stos"]
+        );
+    }
+
+    #[test]
+    fn from_lines_section_with_no_symbols_ok() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section .text:
+            	(No symbols)
+            Disassembly of section .init:
+            <sym1>:
+            	opc1
+        "};
+
+        let result = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                keep_order: true,
+                ..Default::default()
+            },
+        );
+
+        let mut init = Section::new(".init");
+        init.add_symbol(Symbol::new("<sym1>"));
+        let _ = init.add_instruction(Instruction::new("opc1", "", ""));
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [Section::new(".text"), init])
+        )
+    }
+
+    #[test]
+    fn skipped_lines_keep_input_line_numbers_ok() {
+        let lines = indoc! {r"
+            banner
+            folder\file:     file format some_format
+
+            Disassembly of section sec1:
+
+            <sym1>:
+            	opc1
+            ???
+
+            	opc2
+        "};
+
+        let disasm = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                lenient: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(disasm.skipped_lines(), [(8, "???".to_string())]);
+        assert_eq!(disasm.summary().instructions, 2);
+    }
+
+    #[test]
+    fn from_lines_strict_unrecognized_line_fails() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section sec1:
+            some banner
+        "};
+
+        let result = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                lenient: false,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 3: Unrecognized format for the following line: 'some banner'"
+                    .to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn try_from_skip_line_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+                opc1
+                ...
+                opc2    %opr1,%opr2
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines).unwrap();
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<sym1>"));
+        let _ = sec1.add_instruction(Instruction::new("opc1", "", ""));
+        let _ = sec1.add_instruction(Instruction::new_skip());
+        let _ = sec1.add_instruction(Instruction::new("opc2", "%opr1,%opr2", ""));
+        let expected = disasm_with("folder\\file", "some_format", [sec1]);
+
+        assert_eq!(result, expected);
+        assert_eq!(
+            result.to_string(),
+            indoc! {"
+                sec1:
+                    <sym1>:
+                        opc1
+                        ...
+                        opc2
+            "}
+            .to_string()
+        )
+    }
+
+    #[test]
+    fn from_lines_intel_syntax_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            	mov    rax,QWORD PTR [rip+0x2fd9]        # <__gmon_start__@Base>
+            	push   rax
+            	bnd jmp QWORD PTR [rip+0x2f9b]        # <_GLOBAL_OFFSET_TABLE_+0x10>
+            	cs nop WORD PTR [rax+rax*1+0x0]
+            	lea    rdi,[rip+0xca]        ; <main>
+            	ret
+        "};
+
+        let result = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                intel: true,
+                ..Default::default()
+            },
+        );
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<sym1>"));
+        let _ = sec1.add_instruction(Instruction::new(
+            "mov",
+            "rax,QWORD PTR [rip+0x2fd9]",
+            "<__gmon_start__@Base>",
+        ));
+        let _ = sec1.add_instruction(Instruction::new("push", "rax", ""));
+        let _ = sec1.add_instruction(Instruction::new(
+            "bnd jmp",
+            "QWORD PTR [rip+0x2f9b]",
+            "<_GLOBAL_OFFSET_TABLE_+0x10>",
+        ));
+        let _ = sec1.add_instruction(Instruction::new("cs nop", "WORD PTR [rax+rax*1+0x0]", ""));
+        let _ = sec1.add_instruction(Instruction::new("lea", "rdi,[rip+0xca]", "<main>"));
+        let _ = sec1.add_instruction(Instruction::new("ret", "", ""));
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [sec1])
+        )
+    }
+
+    #[test]
+    fn from_lines_llvm_ok() {
+        let lines = indoc! {"
+
+            folder/file.o:\tfile format elf64-x86-64
+
+            Disassembly of section .init:
+
+            <_init>:
+                           \tendbr64
+                           \tsubq\t$8, %rsp
+                           \tmovq\t12249(%rip), %rax       # 0x3fe8 <_GLOBAL_OFFSET_TABLE_+0x30>
+                           \tje\t0x1016 <_init+0x16>
+                           \trepne\t\tjmpq\t*12187(%rip)    # 0x3fc8 <_GLOBAL_OFFSET_TABLE_+0x10>
+                           \tmovq\t128471(%rip), %xmm0     # xmm0 = mem[0],zero
+                                                            # 0x245e0 <optarg>
+                           \tretq
+        "};
+        let options = ParseOptions {
+            backend: Backend::Llvm,
+            ..Default::default()
+        };
+
+        let result = Disasm::from_lines(lines.lines(), &options);
+
+        let mut sec1 = Section::new(".init");
+        sec1.add_symbol(Symbol::new("<_init>"));
+        let _ = sec1.add_instruction(Instruction::new("endbr64", "", ""));
+        let _ = sec1.add_instruction(Instruction::new("subq", "$8, %rsp", ""));
+        let _ = sec1.add_instruction(Instruction::new(
+            "movq",
+            "12249(%rip), %rax",
+            "0x3fe8 <_GLOBAL_OFFSET_TABLE_+0x30>",
+        ));
+        let _ = sec1.add_instruction(Instruction::new("je", "0x1016 <_init+0x16>", ""));
+        let _ = sec1.add_instruction(Instruction::new(
+            "repne jmpq",
+            "*12187(%rip)",
+            "0x3fc8 <_GLOBAL_OFFSET_TABLE_+0x10>",
+        ));
+        let _ = sec1.add_instruction(Instruction::new(
+            "movq",
+            "128471(%rip), %xmm0",
+            "xmm0 = mem[0],zero; 0x245e0 <optarg>",
+        ));
+        let _ = sec1.add_instruction(Instruction::new("retq", "", ""));
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder/file.o", "elf64-x86-64", [sec1])
+        )
+    }
+
+    #[test]
+    fn from_lines_llvm_intel_addresses_raw_bytes_ok() {
+        let lines = indoc! {"
+            folder/file.o:\tfile format elf64-x86-64
+            Disassembly of section .init:
+            0000000000001000 <_init>:
+                1004: 48 83 ec 08                  \tsub\trsp, 8
+                1008: 48 8b 05 d9 2f 00 00         \tmov\trax, qword ptr [rip + 12249] # 0x3fe8 <_DYNAMIC>
+        "};
+        let options = ParseOptions {
+            backend: Backend::Llvm,
+            intel: true,
+            raw_bytes: true,
+            ..Default::default()
+        };
+
+        let result = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        let instructions = result
+            .sections()
+            .flat_map(|sec| sec.symbols())
+            .flat_map(|sym| sym.instructions())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            instructions,
+            [
+                &Instruction::new("sub", "rsp, 8", "")
+                    .with_address(0x1004)
+                    .with_raw_bytes(vec![0x48, 0x83, 0xec, 0x08]),
+                &Instruction::new("mov", "rax, qword ptr [rip + 12249]", "0x3fe8 <_DYNAMIC>")
+                    .with_address(0x1008)
+                    .with_raw_bytes(vec![0x48, 0x8b, 0x05, 0xd9, 0x2f, 0x00, 0x00]),
+            ]
+        )
+    }
+
+    #[test]
+    fn try_from_relocations_ok() {
+        let lines = indoc! {"
+            folder/file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <main>:
+            \tlea    0x0(%rip),%rax        # <main+0xb>
+            \t\t\tR_X86_64_PC32\t.rodata-0x4
+            \tcall   <main+0x13>
+            \t\t\tf: R_X86_64_PLT32\tputs-0x4
+            \tret
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines).unwrap();
+
+        let relocs = result
+            .sections()
+            .flat_map(|sec| sec.symbols())
+            .flat_map(|sym| sym.instructions())
+            .map(|ins| ins.get_reloc())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            relocs,
+            [
+                Some("R_X86_64_PC32 .rodata-0x4"),
+                Some("R_X86_64_PLT32 puts-0x4"),
+                None
+            ]
+        );
+    }
+
+    #[test]
+    fn from_lines_llvm_relocations_ok() {
+        let lines = indoc! {"
+            folder/file.o:\tfile format elf64-x86-64
+            Disassembly of section .text:
+            <main>:
+                           \tcallq\t0x13 <main+0x13>
+            \t\t000000000000000f:  R_X86_64_PLT32\tputs-0x4
+        "};
+        let options = ParseOptions {
+            backend: Backend::Llvm,
+            ..Default::default()
+        };
+
+        let result = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        let mut call = Instruction::new("callq", "0x13 <main+0x13>", "");
+        call.add_reloc("R_X86_64_PLT32 puts-0x4");
+        assert_eq!(
+            result
+                .sections()
+                .flat_map(|sec| sec.symbols())
+                .flat_map(|sym| sym.instructions())
+                .collect::<Vec<_>>(),
+            [&call]
+        );
+    }
+
+    #[test]
+    fn try_from_directives_ok() {
+        let lines = indoc! {"
+            folder/file.o:     file format elf32-littlearm
+            Disassembly of section .text:
+            <main>:
+            \tbx\tlr
+            \t.word\t0x00001234
+              1c:\t.byte\t0x01, 0x02
+            \t.asciz\t\"Hello, world\"
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines).unwrap();
+
+        let mut sec1 = Section::new(".text");
+        sec1.add_symbol(Symbol::new("<main>"));
+        let _ = sec1.add_instruction(Instruction::new("bx", "lr", ""));
+        let _ = sec1.add_instruction(Instruction::new_directive(".word", "0x00001234"));
+        let _ = sec1
+            .add_instruction(Instruction::new_directive(".byte", "0x01, 0x02").with_address(0x1c));
+        let _ = sec1.add_instruction(Instruction::new_directive(".asciz", "\"Hello, world\""));
+        assert_eq!(result.sections, Vec::from([sec1]));
+        assert_eq!(
+            result.to_string(),
+            indoc! {"
+                .text:
+                    <main>:
+                        bx
+                        .word  0x00001234
+                        .byte  0x01, 0x02
+                        .asciz \"Hello, world\"
+            "}
+        );
+        assert_eq!(
+            result.opcode_histogram(),
+            BTreeMap::from([("bx".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn try_from_uppercase_opcodes_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format elf32-littlearm
+            Disassembly of section .text:
+            <main>:
+            	STMFD	sp!,{r4-r6,lr}
+            	LDR.W	r3,[r7,#4]
+            	adds.n	r0,#1
+            	BX	lr
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        let mut sec1 = Section::new(".text");
+        sec1.add_symbol(Symbol::new("<main>"));
+        let _ = sec1.add_instruction(Instruction::new("STMFD", "sp!,{r4-r6,lr}", ""));
+        let _ = sec1.add_instruction(Instruction::new("LDR.W", "r3,[r7,#4]", ""));
+        let _ = sec1.add_instruction(Instruction::new("adds.n", "r0,#1", ""));
+        let _ = sec1.add_instruction(Instruction::new("BX", "lr", ""));
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "elf32-littlearm", [sec1])
+        )
+    }
+
+    #[test]
+    fn try_from_instructions_with_address_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            0000000000001000 <sym1>:
+                1000:\tendbr64
+                1008:\tmov    0x2fd9(%rip),%rax        # 3fe8 <__gmon_start__@Base>
+                1012:\tje     1016 <_init+0x16>
+                1014:\tbnd jmp 1020 <_init+0x20>
+            ffffffff81000000:\tret
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<sym1>").with_address(0x1000));
+        let _ = sec1.add_instruction(Instruction::new("endbr64", "", "").with_address(0x1000));
+        let _ = sec1.add_instruction(
+            Instruction::new("mov", "0x2fd9(%rip),%rax", "3fe8 <__gmon_start__@Base>")
+                .with_address(0x1008),
+        );
+        let _ = sec1
+            .add_instruction(Instruction::new("je", "1016 <_init+0x16>", "").with_address(0x1012));
+        let _ = sec1.add_instruction(
+            Instruction::new("bnd jmp", "1020 <_init+0x20>", "").with_address(0x1014),
+        );
+        let _ =
+            sec1.add_instruction(Instruction::new("ret", "", "").with_address(0xffffffff81000000));
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [sec1])
+        )
+    }
+
+    #[test]
+    fn from_lines_raw_bytes_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            \tc3                   \tret
+            \t48 8b 05 ad ff 01 00 \tmov    0x1ffad(%rip),%rax        # <__gmon_start__@Base>
+            \t66 2e 0f 1f 84 00 00 \tcs nopw 0x0(%rax,%rax,1)
+            \t00 00 00 
+            \tendbr64
+                1000:\tff d0                \tcall   *%rax
+        "};
+
+        let result = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                raw_bytes: true,
+                ..Default::default()
+            },
+        );
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<sym1>"));
+        let _ = sec1.add_instruction(Instruction::new("ret", "", "").with_raw_bytes(vec![0xc3]));
+        let _ = sec1.add_instruction(
+            Instruction::new("mov", "0x1ffad(%rip),%rax", "<__gmon_start__@Base>")
+                .with_raw_bytes(vec![0x48, 0x8b, 0x05, 0xad, 0xff, 0x01, 0x00]),
+        );
+        let _ = sec1.add_instruction(
+            Instruction::new("cs nopw", "0x0(%rax,%rax,1)", "").with_raw_bytes(vec![
+                0x66, 0x2e, 0x0f, 0x1f, 0x84, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ]),
+        );
+        let _ = sec1.add_instruction(Instruction::new("endbr64", "", ""));
+        let _ = sec1.add_instruction(
+            Instruction::new("call", "*%rax", "")
+                .with_address(0x1000)
+                .with_raw_bytes(vec![0xff, 0xd0]),
+        );
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [sec1])
+        )
+    }
+
+    #[test]
+    fn from_lines_raw_bytes_before_instruction_fails() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            \t00 00 00
+        "};
+
+        let result = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                raw_bytes: true,
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 4: Attempted to add raw bytes without first defining an instruction"
+                    .to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn from_lines_demangle_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <_ZN4core3fmt9Formatter3pad17h0123456789abcdefE>:
+            <main>:
+            <_ZN5alloc3vec12Vec$LT$T$GT$4push17h0123456789abcdefE>:
+            <_Z3fooi>:
+        "};
+
+        let result = Disasm::from_lines(
+            lines.lines(),
+            &ParseOptions {
+                demangle: Some(Demangler::Auto),
+                ..Default::default()
+            },
+        );
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<alloc::vec::Vec<T>::push>"));
+        sec1.add_symbol(Symbol::new("<core::fmt::Formatter::pad>"));
+        sec1.add_symbol(Symbol::new("<foo(int)>"));
+        sec1.add_symbol(Symbol::new("<main>"));
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [sec1])
+        )
+    }
+
+    #[test]
+    fn try_from_symbol_before_section_fails() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+
+            <sym1>:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 3: Attempted to add a symbol without first defining a section"
+                    .to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn try_from_instruction_before_section_fails() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+
+            	opc1
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        assert_eq!(
+            result.map_err(|error| error.to_string()),
+            Err(
+                "Error on line 3: Attempted to add an instruction without first defining a section"
+                    .to_string()
+            )
+        )
+    }
+
+    #[test]
+    fn from_lines_sorting_sections_and_symbols_ok() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section abb:
+            <zsym1>:
+            <asym2>:
+            <bsym4>:
+            <bsym3>:
+            Disassembly of section aaa:
+            <sym3>:
+            <sym1>:
+            Disassembly of section adc:
+            Disassembly of section abc:
+            Disassembly of section acc:
+        "}
+        .to_string();
+
+        let result = Disasm::try_from(lines);
+
+        let mut sec1 = Section::new("aaa");
+        sec1.add_symbol(Symbol::new("<sym1>"));
+        sec1.add_symbol(Symbol::new("<sym3>"));
+        let mut sec2 = Section::new("abb");
+        sec2.add_symbol(Symbol::new("<asym2>"));
+        sec2.add_symbol(Symbol::new("<bsym3>"));
+        sec2.add_symbol(Symbol::new("<bsym4>"));
+        sec2.add_symbol(Symbol::new("<zsym1>"));
+        let sec3 = Section::new("abc");
+        let sec4 = Section::new("acc");
+        let sec5 = Section::new("adc");
+
+        assert_eq!(
+            result.unwrap(),
+            disasm_with(
+                "folder\\file",
+                "some_format",
+                [sec1, sec2, sec3, sec4, sec5]
+            )
+        )
+    }
+
+    #[test]
+    fn from_lines_keep_order_ok() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section abb:
+            <zsym1>:
+            <asym2>:
+            Disassembly of section aaa:
+            <sym3>:
+            <sym1>:
+        "};
+        let options = ParseOptions {
+            keep_order: true,
+            ..Default::default()
+        };
+
+        let result = Disasm::from_lines(lines.lines(), &options);
+
+        let mut sec1 = Section::new("abb");
+        sec1.add_symbol(Symbol::new("<zsym1>"));
+        sec1.add_symbol(Symbol::new("<asym2>"));
+        let mut sec2 = Section::new("aaa");
+        sec2.add_symbol(Symbol::new("<sym3>"));
+        sec2.add_symbol(Symbol::new("<sym1>"));
+        assert_eq!(
+            result.unwrap(),
+            disasm_with("folder\\file", "some_format", [sec1, sec2])
+        )
+    }
+
+    #[test]
+    fn from_lines_duplicate_sections_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <main>:
+            \tret
+            Disassembly of section .init:
+            <_init>:
+            Disassembly of section .text:
+            <foo>:
+            \tnop
+        "};
+
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+        assert_eq!(disasm.len(), 3);
+        assert_eq!(disasm.find_duplicate_sections(), [".text"]);
+    }
+
+    #[test]
+    fn from_lines_merge_duplicate_sections_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <main>:
+            \tret
+            Disassembly of section .init:
+            <_init>:
+            Disassembly of section .text:
+            <foo>:
+            \tnop
+        "};
+        let options = ParseOptions {
+            keep_order: true,
+            merge_duplicate_sections: true,
+            ..Default::default()
+        };
+
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        let mut init = Section::new(".init");
+        init.add_symbol(Symbol::new("<_init>"));
+        let mut text = Section::new(".text");
+        text.add_symbol(Symbol::new("<main>"));
+        let _ = text.add_instruction(Instruction::new("ret", "", ""));
+        text.add_symbol(Symbol::new("<foo>"));
+        let _ = text.add_instruction(Instruction::new("nop", "", ""));
+        assert_eq!(disasm, disasm_with("file.o", "elf64-x86-64", [text, init]));
+        assert!(disasm.find_duplicate_sections().is_empty());
+    }
+
+    #[test]
+    fn from_lines_sort_by_address_ok() {
+        let lines = indoc! {r"
+            folder\file:     file format some_format
+            Disassembly of section sec:
+            0000000000001080 <b>:
+            0000000000001040 <c>:
+            <a>:
+        "};
+        let options = ParseOptions {
+            sort_by: SortKey::Address,
+            ..Default::default()
+        };
+
+        let result = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        assert_eq!(
+            result
+                .sections()
+                .flat_map(|sec| sec.symbols())
+                .map(|sym| sym.get_name().as_str())
+                .collect::<Vec<_>>(),
+            ["<c>", "<b>", "<a>"]
+        )
+    }
+
+    #[test]
+    fn sections_len_and_is_empty_ok() {
+        let disasm = disasm_with(
+            "folder\\file",
+            "some_format",
+            [Section::new("sec1"), Section::new("sec2")],
+        );
+
+        assert_eq!(
+            disasm
+                .sections()
+                .map(|sec| sec.get_name())
+                .collect::<Vec<_>>(),
+            ["sec1", "sec2"]
+        );
+        assert_eq!(disasm.len(), 2);
+        assert!(!disasm.is_empty());
+    }
+
+    /// Disassembly made of `sections`, built through the public API so that the section index is consistent.
+    fn disasm_with(
+        file_name: &str,
+        file_format: &str,
+        sections: impl IntoIterator<Item = Section>,
+    ) -> Disasm {
+        let mut disasm = Disasm::new(file_name, file_format);
+        for section in sections {
+            disasm.add_section(section);
+        }
+        disasm
+    }
+
+    fn retain_sections_fixture() -> Disasm {
+        disasm_with(
+            "folder\\file",
+            "some_format",
+            [
+                Section::new(".data"),
+                Section::new(".init"),
+                Section::new(".text"),
+            ],
+        )
+    }
+
+    #[test]
+    fn retain_sections_single_match_ok() {
+        let mut disasm = retain_sections_fixture();
+        disasm.retain_sections(&[".text".to_string()]);
+        assert_eq!(disasm.sections, Vec::from([Section::new(".text")]));
+    }
+
+    #[test]
+    fn retain_sections_multiple_matches_ok() {
+        let mut disasm = retain_sections_fixture();
+        disasm.retain_sections(&[".text".to_string(), ".data".to_string()]);
+        assert_eq!(
+            disasm.sections,
+            Vec::from([Section::new(".data"), Section::new(".text")])
+        );
+    }
+
+    #[test]
+    fn retain_sections_no_match_ok() {
+        let mut disasm = retain_sections_fixture();
+        disasm.retain_sections(&[".bss".to_string()]);
+        assert_eq!(disasm.sections, Vec::new());
+    }
+
+    #[test]
+    fn retain_symbols_matching_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <_ZN4main7handlerE>:
+            <_ZN4main5otherE>:
+            Disassembly of section sec2:
+            <_ZN3foo11irq_handlerE>:
+            Disassembly of section sec3:
+            <sym1>:
+        "}
+        .to_string();
+        let mut disasm = Disasm::try_from(lines).unwrap();
+
+        disasm.retain_symbols_matching(&Regex::new(r"^<_ZN.*handler.*>$").unwrap());
+
+        let mut sec1 = Section::new("sec1");
+        sec1.add_symbol(Symbol::new("<_ZN4main7handlerE>"));
+        let mut sec2 = Section::new("sec2");
+        sec2.add_symbol(Symbol::new("<_ZN3foo11irq_handlerE>"));
+        let sec3 = Section::new("sec3");
+        assert_eq!(disasm.sections, Vec::from([sec1, sec2, sec3]));
+    }
+
+    #[test]
+    fn retain_sections_matching_code_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            Disassembly of section .text.unlikely:
+            Disassembly of section .plt.sec:
+            Disassembly of section .init:
+            Disassembly of section .data:
+            Disassembly of section .rodata:
+            Disassembly of section .initdata:
+        "};
+        let mut disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+
+        disasm.retain_sections_matching(&Regex::new(CODE_SECTIONS_PATTERN).unwrap());
+        assert_eq!(
+            disasm
+                .sections()
+                .map(|sec| sec.get_name().as_str())
+                .collect::<Vec<_>>(),
+            [".init", ".plt.sec", ".text", ".text.unlikely"]
+        );
+    }
+
+    #[test]
+    fn retain_symbols_matching_no_match_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            <sym2>:
+        "}
+        .to_string();
+        let mut disasm = Disasm::try_from(lines).unwrap();
+
+        disasm.retain_symbols_matching(&Regex::new(r"handler").unwrap());
+        assert_eq!(disasm.sections, Vec::from([Section::new("sec1")]));
+
+        disasm.drop_empty_sections();
+        assert_eq!(disasm.sections, Vec::new());
+    }
+
+    #[test]
+    fn drop_empty_sections_after_filtering_ok() {
+        let mut disasm = multi_section_fixture();
+
+        disasm.retain_symbols_matching(&Regex::new(r"sym1").unwrap());
+        disasm.drop_empty_sections();
+        assert_eq!(
+            disasm
+                .sections()
+                .map(|sec| sec.get_name().as_str())
+                .collect::<Vec<_>>(),
+            ["sec1"]
+        );
+        assert_eq!(disasm.sections[0].len(), 1);
+    }
+
+    fn multi_section_fixture() -> Disasm {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            \tpush   %rbp
+            \tmov    %rsp,%rbp
+            \tret
+            <sym2>:
+            \tmov    %rdi,%rax
+            \t...
+            Disassembly of section sec2:
+            <sym3>:
+            \tmov    %rax,%rbx
+            \tret
+            <sym4>:
+        "}
+        .to_string();
+        Disasm::try_from(lines).unwrap()
+    }
+
+    #[test]
+    fn file_format_ok() {
+        let lines = indoc! {"
+            hello.o:     file format elf64-x86-64
+            Disassembly of section .text:
+        "};
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+        assert_eq!(disasm.get_file_name(), "hello.o");
+        assert_eq!(disasm.get_file_format(), "elf64-x86-64");
+        assert_eq!(disasm.parsed_file_format().bitness(), Some(64));
+
+        let disasm = multi_section_fixture();
+        assert_eq!(
+            disasm.parsed_file_format(),
+            FileFormat::Other("some_format".to_string())
+        );
+    }
+
+    #[test]
+    fn section_stats_ok() {
+        let mut disasm = multi_section_fixture();
+        disasm.sort(SortKey::Name);
+        disasm.sections.reverse();
+
+        let stats = disasm.section_stats();
+        assert_eq!(
+            stats,
+            [
+                SectionStat {
+                    name: "sec1".to_string(),
+                    symbols: 2,
+                    instructions: 4,
+                    bytes: None,
+                },
+                SectionStat {
+                    name: "sec2".to_string(),
+                    symbols: 2,
+                    instructions: 2,
+                    bytes: None,
+                }
+            ]
+        );
+        let summary = disasm.summary();
+        assert_eq!(
+            stats.iter().map(|stat| stat.symbols).sum::<usize>(),
+            summary.symbols
+        );
+        assert_eq!(
+            stats.iter().map(|stat| stat.instructions).sum::<usize>(),
+            summary.instructions
+        );
+    }
+
+    #[test]
+    fn parse_stats_ok() {
+        let disasm = multi_section_fixture();
+        assert_eq!(
+            disasm.parse_stats(),
+            &ParseStats {
+                sections: 2,
+                symbols: 4,
+                instructions: 6,
+                other: 2,
+                skipped: 0,
+            }
+        );
+
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <main>:
+            ???
+            \tret
+        "};
+        let options = ParseOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+        assert_eq!(disasm.parse_stats().skipped, 1);
+        assert_eq!(disasm.parse_stats().instructions, 1);
+    }
+
+    #[test]
+    fn section_names_ok() {
+        let disasm = multi_section_fixture();
+        assert_eq!(disasm.section_names(), ["sec1", "sec2"]);
+
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            Disassembly of section .plt:
+            Disassembly of section .init:
+        "};
+        let options = ParseOptions {
+            keep_order: true,
+            ..Default::default()
+        };
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+        assert_eq!(disasm.section_names(), [".init", ".plt", ".text"]);
+    }
+
+    #[test]
+    fn section_by_name_ok() {
+        let mut disasm = multi_section_fixture();
+        assert_eq!(disasm.section_by_name("sec2").unwrap().len(), 2);
+        assert!(disasm.section_by_name("sec3").is_none());
+
+        disasm.retain_sections(&[String::from("sec2")]);
+        assert!(disasm.section_by_name("sec1").is_none());
+        assert_eq!(disasm.section_by_name("sec2").unwrap().get_name(), "sec2");
+    }
+
+    #[test]
+    fn section_by_name_many_sections_ok() {
+        let mut lines = vec![String::from("file.o:     file format elf64-x86-64")];
+        for round in 0..2 {
+            for idx in 0..5000 {
+                lines.push(format!("Disassembly of section .text.f{idx}:"));
+                lines.push(format!("<f{idx}_{round}>:"));
+                lines.push(String::from("\tret"));
+            }
+        }
+        let options = ParseOptions {
+            keep_order: true,
+            merge_duplicate_sections: true,
+            ..Default::default()
+        };
+
+        let disasm = Disasm::from_lines(lines.iter().map(String::as_str), &options).unwrap();
+
+        assert_eq!(disasm.len(), 5000);
+        let section = disasm.section_by_name(".text.f4321").unwrap();
+        assert_eq!(
+            section.symbols().map(Symbol::get_name).collect::<Vec<_>>(),
+            ["<f4321_0>", "<f4321_1>"]
+        );
+    }
+
+    #[test]
+    fn retain_instructions_matching_and_drop_empty_symbols_ok() {
+        let mut disasm = multi_section_fixture();
+        disasm.retain_instructions_matching(&["push".to_string(), "ret".to_string()]);
+        disasm.drop_empty_symbols();
+        assert_eq!(
+            disasm.instruction_counts().collect::<Vec<_>>(),
+            [("sec1", "<sym1>", 2), ("sec2", "<sym3>", 1)]
+        );
+    }
+
+    #[test]
+    fn regroup_by_symbol_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <foo>:
+            \tpush   %rbp
+            \tret
+            <bar>:
+            \tret
+            Disassembly of section .text.hot:
+            <foo>:
+            \tnop
+            <baz>:
+        "};
+        let options = ParseOptions {
+            keep_order: true,
+            ..Default::default()
+        };
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        let push = Instruction::new("push", "%rbp", "");
+        let ret = Instruction::new("ret", "", "");
+        let nop = Instruction::new("nop", "", "");
+        assert_eq!(
+            disasm.regroup_by_symbol().into_iter().collect::<Vec<_>>(),
+            [
+                ("<bar>".to_string(), Vec::from([&ret])),
+                ("<baz>".to_string(), Vec::new()),
+                ("<foo>".to_string(), Vec::from([&push, &ret, &nop])),
+            ]
+        );
+    }
+
+    #[test]
+    fn to_string_by_symbol_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <foo>:
+            \tpush   %rbp
+            \tnop
+            <bar>:
+            \tret
+            Disassembly of section .text.hot:
+            <foo>:
+            \tnop
+            \tnop
+            \tret
+        "};
+        let parse_options = ParseOptions {
+            keep_order: true,
+            ..Default::default()
+        };
+        let disasm = Disasm::from_lines(lines.lines(), &parse_options).unwrap();
+        let options = FormatOptions {
+            operands: true,
+            align: true,
+            collapse_repeats: Some(3),
+            indent: "\t".to_string(),
+            ..Default::default()
+        };
+
+        let expected = indoc! {"
+            <bar>:
+            \tret
+            <foo>:
+            \tpush %rbp
+            \tnop (x 3)
+            \tret
+        "};
+        assert_eq!(disasm.to_string_by_symbol(&options), expected);
+    }
+
+    #[test]
+    fn empty_symbols_ok() {
+        let disasm = multi_section_fixture();
+        assert_eq!(
+            disasm.empty_symbols(),
+            [("sec2".to_string(), "<sym4>".to_string())]
+        );
+    }
+
+    #[test]
+    fn clone_clusters_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <foo>:
+            \tmov    %rdi,%rax
+            \tret
+            <bar>:
+            \tret
+            <empty>:
+            Disassembly of section .text.hot:
+            <foo.hot>:
+            \tmov    %rsi,%rax
+            \tret
+            <empty.hot>:
+        "};
+        let options = ParseOptions {
+            keep_order: true,
+            ..Default::default()
+        };
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
+
+        let clusters = disasm.clone_clusters();
+        let names = clusters
+            .iter()
+            .map(|cluster| {
+                cluster
+                    .iter()
+                    .map(|sym_ref| (sym_ref.section, sym_ref.symbol.get_name().as_str()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(names, [[(".text", "<foo>"), (".text.hot", "<foo.hot>")]]);
+    }
+
+    #[test]
+    fn summary_ok() {
+        let summary = multi_section_fixture().summary();
+        assert_eq!(
+            summary,
+            Summary {
+                file_name: "folder\\file".to_string(),
+                file_format: "some_format".to_string(),
+                sections: 2,
+                symbols: 4,
+                instructions: 6,
+            }
+        );
+    }
+
+    #[test]
+    fn merge_ok() {
+        let first = indoc! {"
+            first.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <main>:
+            \tret
+            Disassembly of section .init:
+            <_init>:
+        "};
+        let second = indoc! {"
+            second.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <foo>:
+            \tnop
+            Disassembly of section .fini:
+            <_fini>:
+        "};
+        let options = ParseOptions::default();
+        let mut disasm = Disasm::from_lines(first.lines(), &options).unwrap();
+        let other = Disasm::from_lines(second.lines(), &options).unwrap();
+
+        disasm.merge(Vec::from([other]));
+
+        let mut init = Section::new(".init");
+        init.add_symbol(Symbol::new("<_init>"));
+        let mut text = Section::new(".text");
+        text.add_symbol(Symbol::new("<main>"));
+        let _ = text.add_instruction(Instruction::new("ret", "", ""));
+        text.add_symbol(Symbol::new("<foo>"));
+        let _ = text.add_instruction(Instruction::new("nop", "", ""));
+        let mut fini = Section::new(".fini");
+        fini.add_symbol(Symbol::new("<_fini>"));
+        assert_eq!(
+            disasm,
+            disasm_with("first.o, second.o", "elf64-x86-64", [init, text, fini])
+        );
+
+        disasm.sort(SortKey::Name);
+        assert_eq!(
+            disasm
+                .sections()
+                .flat_map(|sec| sec.symbols())
+                .map(|sym| sym.get_name().as_str())
+                .collect::<Vec<_>>(),
+            ["<_fini>", "<_init>", "<foo>", "<main>"]
+        );
+    }
+
+    #[test]
+    fn call_edges_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section .plt.sec:
+            <puts@plt>:
+            \tbnd jmp *0x2fe2(%rip)        # 3fd0 <puts@GLIBC_2.2.5>
+            Disassembly of section .text:
+            <main>:
+            \tcall   <helper>
+            \tje     <main+0x16>
+            \tcall   <puts@plt>
+            \tjmp    <helper+0x10>
+            \tcall   <main>
+            <helper>:
+            \tcall   <puts@plt>
+            \tcall   *%rax
+            \tjmp    <external>
+            \tcall   0x4011a0
+        "}
+        .to_string();
+        let disasm = Disasm::try_from(lines).unwrap();
+
+        assert_eq!(
+            disasm.call_edges(),
+            [
+                ("<helper>".to_string(), "<puts@plt>".to_string()),
+                ("<helper>".to_string(), "<external>".to_string()),
+                ("<helper>".to_string(), "0x4011a0".to_string()),
+                ("<main>".to_string(), "<helper>".to_string()),
+                ("<main>".to_string(), "<puts@plt>".to_string()),
+                ("<main>".to_string(), "<main>".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn category_stats_ok() {
+        let disasm = multi_section_fixture();
+        assert_eq!(
+            disasm.category_stats(),
+            BTreeMap::from([
+                (OpcodeCategory::Branch, 2),
+                (OpcodeCategory::Memory, 3),
+                (OpcodeCategory::Stack, 1),
+            ])
+        );
+    }
+
+    #[test]
+    fn opcode_histogram_ok() {
+        let disasm = multi_section_fixture();
+        assert_eq!(
+            disasm.opcode_histogram(),
+            BTreeMap::from([
+                ("mov".to_string(), 3),
+                ("push".to_string(), 1),
+                ("ret".to_string(), 2),
+            ])
+        );
+    }
+
+    #[test]
+    fn instruction_counts_ok() {
+        let disasm = multi_section_fixture();
+        assert_eq!(
+            disasm.instruction_counts().collect::<Vec<_>>(),
+            [
+                ("sec1", "<sym1>", 3),
+                ("sec1", "<sym2>", 1),
+                ("sec2", "<sym3>", 2),
+                ("sec2", "<sym4>", 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn iter_symbols_ok() {
+        let mut disasm = multi_section_fixture();
+        disasm.add_section(Section::new("sec3"));
+        assert_eq!(
+            disasm.iter_symbols().collect::<Vec<_>>(),
+            [
+                ("sec1", "<sym1>"),
+                ("sec1", "<sym2>"),
+                ("sec2", "<sym3>"),
+                ("sec2", "<sym4>"),
+            ]
+        );
+    }
+
+    #[test]
+    fn opcode_histogram_empty_opcodes_ignored_ok() {
+        let mut section = Section::new("sec1");
+        section.add_symbol(Symbol::new("<sym1>"));
+        let _ = section.add_instruction(Instruction::new("", "", ""));
+        let _ = section.add_instruction(Instruction::new("nop", "", ""));
+        let disasm = disasm_with("file", "some_format", [section]);
+        assert_eq!(
+            disasm.opcode_histogram(),
+            BTreeMap::from([("nop".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn diff_ok() {
+        let old = multi_section_fixture();
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+            \tpush   %rbp
+            \tmov    %rsp,%rbp
+            \tret
+            <sym2>:
+            \tmov    %rsi,%rax
+            \tret
+            Disassembly of section sec2:
+            <sym3>:
+            \tmov    %rbx,%rax
+            \tret
+            <sym5>:
+            \tnop
+            Disassembly of section sec3:
+            <sym6>:
+        "}
+        .to_string();
+        let new = Disasm::try_from(lines).unwrap();
+
+        let report = old.diff(&new);
+
+        let mut sec1 = SectionDiff::new("sec1");
+        sec1.add_changed("<sym2>");
+        let mut sec2 = SectionDiff::new("sec2");
+        sec2.add_removed("<sym4>");
+        sec2.add_added("<sym5>");
+        let mut sec3 = SectionDiff::new("sec3");
+        sec3.add_added("<sym6>");
+        let mut expected = DiffReport::new();
+        expected.add_section(sec1);
+        expected.add_section(sec2);
+        expected.add_section(sec3);
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn diff_duplicate_sections_ok() {
+        let old = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <a>:
+            \tret
+            Disassembly of section .text:
+            <b>:
+            \tret
+        "}
+        .to_string();
+        let new = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <a>:
+            \tret
+            Disassembly of section .text:
+            <b>:
+            \tnop
+            \tret
+            <c>:
+            \tret
+        "}
+        .to_string();
+        let old = Disasm::try_from(old).unwrap();
+        let new = Disasm::try_from(new).unwrap();
+        assert_eq!(new.sections().count(), 2);
+
+        let report = old.diff(&new);
+
+        let mut text = SectionDiff::new(".text");
+        text.add_changed("<b>");
+        text.add_added("<c>");
+        let mut expected = DiffReport::new();
+        expected.add_section(text);
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn diff_repeated_symbols_ok() {
+        let old = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <dup>:
+            \tret
+            <dup>:
+            \tnop
+            <gone>:
+            \tret
+            <gone>:
+            \tret
+        "}
+        .to_string();
+        let new = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <dup>:
+            \tret
+            <dup>:
+            \tnop
+            \tnop
+            <dup>:
+            \tret
+            <gone>:
+            \tret
+        "}
+        .to_string();
+        let options = ParseOptions {
+            keep_order: true,
+            ..Default::default()
+        };
+        let old = Disasm::from_lines(old.lines(), &options).unwrap();
+        let new = Disasm::from_lines(new.lines(), &options).unwrap();
+
+        let report = old.diff(&new);
+
+        let mut text = SectionDiff::new(".text");
+        text.add_changed("<dup>");
+        text.add_removed("<gone>");
+        text.add_added("<dup>");
+        let mut expected = DiffReport::new();
+        expected.add_section(text);
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn diff_hashes_ok() {
+        let mut previous = multi_section_fixture().symbol_hashes(false);
+        assert_eq!(
+            previous.keys().collect::<Vec<_>>(),
+            ["sec1/<sym1>", "sec1/<sym2>", "sec2/<sym3>", "sec2/<sym4>"]
+        );
+        previous.insert("sec1/<sym2>".to_string(), 0);
+        previous.remove("sec2/<sym3>");
+        previous.insert("sec3/<gone>".to_string(), 0);
+
+        let report = multi_section_fixture().diff_hashes(&previous, false);
+
+        let mut sec1 = SectionDiff::new("sec1");
+        sec1.add_changed("<sym2>");
+        let mut sec2 = SectionDiff::new("sec2");
+        sec2.add_added("<sym3>");
+        let mut sec3 = SectionDiff::new("sec3");
+        sec3.add_removed("<gone>");
+        let mut expected = DiffReport::new();
+        expected.add_section(sec1);
+        expected.add_section(sec2);
+        expected.add_section(sec3);
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn diff_identical_ok() {
+        let report = multi_section_fixture().diff(&multi_section_fixture());
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn to_string_ok() {
+        let mut sec1 = Section::new("aaa");
+        sec1.add_symbol(Symbol::new("<sym1>"));
+        sec1.add_symbol(Symbol::new("<sym3>"));
+        let _ = sec1.add_instruction(Instruction::new("opc5", "", ""));
+        let _ = sec1.add_instruction(Instruction::new("opc3", "", ""));
+        let sec2 = Section::new("abc");
+        let mut sec3 = Section::new("abb");
+        sec3.add_symbol(Symbol::new("<zsym2>"));
+        let _ = sec3.add_instruction(Instruction::new("opc1", "", ""));
+        let _ = sec3.add_instruction(Instruction::new("opc2", "opr1,opr2", ""));
+        let _ = sec3.add_instruction(Instruction::new("opc4", "opr3", "comment1"));
+        sec3.add_symbol(Symbol::new("<asym1>"));
+        let disasm = disasm_with("folder\\file", "some_format", [sec1, sec2, sec3]);
+
+        assert_eq!(
+            disasm.to_string(),
+            indoc! {"
+                aaa:
+                    <sym1>:
+                    <sym3>:
+                        opc5
+                        opc3
+                abc:
+                abb:
+                    <zsym2>:
+                        opc1
+                        opc2
+                        opc4
+                    <asym1>:
+            "}
+            .to_string()
+        )
+    }
+
+    #[test]
+    fn to_string_with_operands_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+                opc1
+                opc2    %opr1,%opr2
+                opc3    %opr3                   # comment1
+        "}
+        .to_string();
+
+        let disasm = Disasm::try_from(lines).unwrap();
+
+        assert_eq!(
+            disasm.to_string_with(&FormatOptions {
+                operands: true,
+                ..Default::default()
+            }),
+            indoc! {"
+                sec1:
+                    <sym1>:
+                        opc1
+                        opc2   %opr1,%opr2
+                        opc3   %opr3
+            "}
+            .to_string()
+        )
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use indoc::indoc;
 
     #[test]
-    fn try_from_simple_corect_file_ok() {
+    fn to_string_with_comments_ok() {
         let lines = indoc! {"
             folder\\file:     file format some_format
-
-
             Disassembly of section sec1:
-
             <sym1>:
                 opc1
                 opc2    %opr1,%opr2
                 opc3    %opr3                   # comment1
-
-            <sym2>:
-                opc4   %opr4  # comment2
-
-            Disassembly of section sec2:
-
-            <sym3>:
         "}
         .to_string();
 
-        let result = Disasm::try_from(lines);
-
-        let mut sec1 = Section::new("sec1");
-        sec1.add_symbol(Symbol::new("<sym1>"));
-        let _ = sec1.add_instruction(Instruction::new("opc1", "", ""));
-        let _ = sec1.add_instruction(Instruction::new("opc2", "%opr1,%opr2", ""));
-        let _ = sec1.add_instruction(Instruction::new("opc3", "%opr3", "comment1"));
-        sec1.add_symbol(Symbol::new("<sym2>"));
-        let _ = sec1.add_instruction(Instruction::new("opc4", "%opr4", "comment2"));
-        let mut sec2 = Section::new("sec2");
-        sec2.add_symbol(Symbol::new("<sym3>"));
+        let disasm = Disasm::try_from(lines).unwrap();
 
         assert_eq!(
-            result,
-            Ok(Disasm {
-                file_name: "folder\\file".to_string(),
-                file_format: "some_format".to_string(),
-                sections: Vec::from([sec1, sec2]),
-            })
+            disasm.to_string_with(&FormatOptions {
+                comments: true,
+                ..Default::default()
+            }),
+            indoc! {"
+                sec1:
+                    <sym1>:
+                        opc1
+                        opc2
+                        opc3        # comment1
+            "}
+            .to_string()
+        );
+        assert_eq!(
+            disasm.to_string_with(&FormatOptions {
+                operands: true,
+                comments: true,
+                ..Default::default()
+            }),
+            indoc! {"
+                sec1:
+                    <sym1>:
+                        opc1
+                        opc2   %opr1,%opr2
+                        opc3   %opr3        # comment1
+            "}
+            .to_string()
         )
     }
 
     #[test]
-    fn try_from_empty_string_fails() {
-        let result = Disasm::try_from("".to_string());
+    fn to_markdown_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section .text:
+            <main>:
+            \tcall   <_Z3fooi@plt>
+            \tret
+            <_start>:
+        "}
+        .to_string();
+        let disasm = Disasm::try_from(lines).unwrap();
+        let options = FormatOptions {
+            operands: true,
+            ..Default::default()
+        };
+
         assert_eq!(
-            result,
-            Err("Error, the file does not contain any text".to_string())
-        )
+            disasm.to_markdown(&options),
+            indoc! {r"
+                ## .text
+
+                ### \<\_start\>
+
+                ### \<main\>
+
+                ```
+                call   <_Z3fooi@plt>
+                ret
+                ```
+
+            "}
+        );
     }
 
     #[test]
-    fn try_from_incorrectly_formatted_first_line_fails() {
-        let result = Disasm::try_from("New line with incorrect formatting".to_string());
+    fn to_flat_string_ok() {
+        let mut disasm = multi_section_fixture();
+        disasm.add_section(Section::new("sec3"));
+        let options = FormatOptions {
+            operands: true,
+            ..Default::default()
+        };
+
         assert_eq!(
-            result,
-            Err("Incorrect format for the first line".to_string())
-        )
+            disasm.to_flat_string(&options),
+            indoc! {"
+                sec1/<sym1>: push   %rbp
+                sec1/<sym1>: mov    %rsp,%rbp
+                sec1/<sym1>: ret
+                sec1/<sym2>: mov    %rdi,%rax
+                sec1/<sym2>: ...
+                sec2/<sym3>: mov    %rax,%rbx
+                sec2/<sym3>: ret
+                sec2/<sym4>:
+                sec3:
+            "}
+        );
     }
 
     #[test]
-    fn try_from_incorrectly_formatted_section_name_fixed_part_fails() {
+    fn to_csv_ok() {
         let lines = indoc! {"
             folder\\file:     file format some_format
-            gibberish of section sec1:
+            Disassembly of section sec1:
+            <sym1>:
+                1000:\tmov    0x0(%rax,%rax,1),%rbx        # 3fe8 <__gmon_start__@Base>
+                1008:\tret
+                ...
         "}
         .to_string();
+        let disasm = Disasm::try_from(lines).unwrap();
 
-        let result = Disasm::try_from(lines);
+        let mut csv = Vec::new();
+        disasm.to_csv(&mut csv).unwrap();
 
         assert_eq!(
-            result,
-            Err(
-                "Unrecognized format for the following line: 'gibberish of section sec1:'"
-                    .to_string()
-            )
-        )
+            String::from_utf8(csv).unwrap(),
+            indoc! {r#"
+                section,symbol,address,opcode,operands,comment
+                sec1,<sym1>,1000,mov,"0x0(%rax,%rax,1),%rbx",3fe8 <__gmon_start__@Base>
+                sec1,<sym1>,1008,ret,,
+            "#}
+        );
     }
 
     #[test]
-    fn try_from_incorrectly_formatted_section_name_strange_sec_name_fails() {
-        let lines = indoc! {"
-            folder\\file:     file format some_format
-            Disassembly of section sec%1:
-        "}
-        .to_string();
+    fn to_objdump_string_round_trip_ok() {
+        let text = indoc! {"
 
-        let result = Disasm::try_from(lines);
+            file.o:     file format elf64-x86-64
 
-        assert_eq!(
-            result,
-            Err(
-                "Unrecognized format for the following line: 'Disassembly of section sec%1:'"
-                    .to_string()
-            )
-        )
+
+            Disassembly of section .text:
+
+            0000000000001000 <sym2>:
+                1000:\t48 8b 05 d9 2f 00 00 \tmov    0x2fd9(%rip),%rax        # 3fe8 <__gmon_start__@Base>
+                1007:\t66 2e 0f 1f 84 00 00 \tcs nopw 0x0(%rax,%rax,1)
+                100e:\t00 00 00 
+                1011:\tc3                   \tret
+            \t...
+
+            0000000000000f00 <sym1>:
+                 f00:\te8 00 00 00 00       \tcall   f05 <sym1+0x5>
+
+            Disassembly of section .init:
+
+            0000000000000e00 <_init>:
+             e00:\tf3 0f 1e fa          \tendbr64
+        "};
+        let options = ParseOptions {
+            raw_bytes: true,
+            keep_order: true,
+            ..Default::default()
+        };
+
+        let disasm = Disasm::from_lines(text.lines(), &options).unwrap();
+
+        assert_eq!(disasm.to_objdump_string(), text);
     }
 
     #[test]
-    fn try_from_incorrectly_formatted_symbol_missing_start_lt_fails() {
-        let lines = indoc! {r"
-            folder\file:     file format some_format
-            Disassembly of section sec1:
-            sym1>:
-        "}
-        .to_string();
+    fn to_objdump_string_round_trip_32_bits_ok() {
+        for file_format in ["elf32-i386", "pe-i386"] {
+            let text = formatdoc! {"
 
-        let result = Disasm::try_from(lines);
+                file.o:     file format {file_format}
+
+
+                Disassembly of section .text:
+
+                00000000 <f>:
+                   0:\t55                   \tpush   %ebp
+                   3:\tc7 45 10 78 56 34 12 \tmovl   $0x12345678,0x10(%ebp)
+                   a:\te8 00 00 00 00       \tcall   f <g>
+
+                0000000f <g>:
+                   f:\tc3                   \tret
+
+                Disassembly of section .data:
+
+                10000000 <big>:
+                10000000:\t90                   \tnop
+            "};
+            let options = ParseOptions {
+                raw_bytes: true,
+                keep_order: true,
+                ..Default::default()
+            };
+
+            let disasm = Disasm::from_lines(text.lines(), &options).unwrap();
+
+            assert_eq!(disasm.to_objdump_string(), text, "{file_format}");
+        }
+    }
+
+    #[test]
+    fn write_colored_ok() {
+        let disasm = multi_section_fixture();
+        let options = FormatOptions {
+            operands: true,
+            ..Default::default()
+        };
 
+        let mut plain = termcolor::NoColor::new(Vec::new());
+        disasm.write_colored(&mut plain, &options).unwrap();
         assert_eq!(
-            result,
-            Err("Unrecognized format for the following line: 'sym1>:'".to_string())
-        )
+            String::from_utf8(plain.into_inner()).unwrap(),
+            disasm.to_string_with(&options)
+        );
+
+        let mut colored = termcolor::Ansi::new(Vec::new());
+        disasm.write_colored(&mut colored, &options).unwrap();
+        let colored = String::from_utf8(colored.into_inner()).unwrap();
+        assert!(colored.starts_with("\x1b[0m\x1b[1m\x1b[34msec1\x1b[0m:\n"));
+        assert!(colored.contains("    \x1b[0m\x1b[32m<sym1>\x1b[0m:\n"));
     }
 
     #[test]
-    fn try_from_incorrectly_formatted_symbol_missing_end_fails() {
-        let lines = indoc! {r"
-            folder\file:     file format some_format
-            Disassembly of section sec1:
-            <sym1
-        "}
-        .to_string();
+    fn write_to_ok() {
+        let disasm = multi_section_fixture();
+        let options = FormatOptions {
+            operands: true,
+            ..Default::default()
+        };
 
-        let result = Disasm::try_from(lines);
+        let mut out = Vec::new();
+        disasm.write_to(&mut out, &options).unwrap();
 
-        assert_eq!(
-            result,
-            Err("Unrecognized format for the following line: '<sym1'".to_string())
-        )
+        assert_eq!(out, disasm.to_string_with(&options).into_bytes());
+        out.clear();
+        disasm
+            .write_to(&mut out, &FormatOptions::default())
+            .unwrap();
+        assert_eq!(out, disasm.to_string().into_bytes());
     }
 
     #[test]
-    fn try_from_incorrectly_formatted_instruction_missing_leading_space_fails() {
-        let lines = indoc! {r"
-            folder\file:     file format some_format
+    fn serialize_json_strip_comments_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
             Disassembly of section sec1:
             <sym1>:
-            opc1 opc2    %opr1,%opr2          # comment1
-        "}
-        .to_string();
+                opc2    %opr1,%opr2      # 1040 <secret_path>
+        "};
+        let options = ParseOptions {
+            strip_comments: true,
+            ..Default::default()
+        };
 
-        let result = Disasm::try_from(lines);
+        let disasm = Disasm::from_lines(lines.lines(), &options).unwrap();
 
+        let json = serde_json::to_value(&disasm).unwrap();
         assert_eq!(
-            result,
-            Err("Unrecognized format for the following line: 'opc1 opc2    %opr1,%opr2          # comment1'".to_string())
-        )
+            json["sections"][0]["symbols"][0]["instructions"][0]["comment"],
+            ""
+        );
+        assert!(!json.to_string().contains("secret_path"));
     }
 
     #[test]
-    fn try_from_incorrectly_formatted_instruction_incorrect_opcode_format_fails() {
-        let lines = indoc! {r"
-            folder\file:     file format some_format
-            Disassembly of section sec1:
-            <sym1>:
-            	Opc1 opc2    %opr1,%opr2          # comment1
-        "}
-        .to_string();
+    fn from_lines_instruction_prefixes_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <f>:
+            \txacquire lock addl $0x1,(%rax)
+            \txrelease movl $0x0,(%rax)
+            \trex.W nop
+            \t{vex} vpdpbusd %ymm2,%ymm1,%ymm0
+            \tbnd jmp 1020 <_init+0x20>
+        "};
+        let intel_lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <f>:
+            \txacquire lock add DWORD PTR [rax],0x1
+            \trex.W nop
+            \t{vex} vpdpbusd ymm0,ymm1,ymm2
+        "};
+        let intel_options = ParseOptions {
+            intel: true,
+            ..Default::default()
+        };
 
-        let result = Disasm::try_from(lines);
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+        let intel_disasm = Disasm::from_lines(intel_lines.lines(), &intel_options).unwrap();
 
+        let instructions = disasm.sections[0]
+            .symbols()
+            .flat_map(|symbol| symbol.instructions())
+            .collect::<Vec<_>>();
         assert_eq!(
-            result,
-            Err("Unrecognized format for the following line: '	Opc1 opc2    %opr1,%opr2          # comment1'".to_string())
-        )
+            instructions,
+            [
+                &Instruction::new("xacquire lock addl", "$0x1,(%rax)", ""),
+                &Instruction::new("xrelease movl", "$0x0,(%rax)", ""),
+                &Instruction::new("rex.W nop", "", ""),
+                &Instruction::new("{vex} vpdpbusd", "%ymm2,%ymm1,%ymm0", ""),
+                &Instruction::new("bnd jmp", "1020 <_init+0x20>", ""),
+            ]
+        );
+        let intel_instructions = intel_disasm.sections[0]
+            .symbols()
+            .flat_map(|symbol| symbol.instructions())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            intel_instructions,
+            [
+                &Instruction::new("xacquire lock add", "DWORD PTR [rax],0x1", ""),
+                &Instruction::new("rex.W nop", "", ""),
+                &Instruction::new("{vex} vpdpbusd", "ymm0,ymm1,ymm2", ""),
+            ]
+        );
     }
 
     #[test]
-    fn try_from_symbol_before_section_fails() {
-        let lines = indoc! {r"
-            folder\file:     file format some_format
-
-            <sym1>:
-        "}
-        .to_string();
+    fn from_lines_demangled_names_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            0000000000000000 <std::vector<int>::push_back(int&&)>:
+               0:\tcall   1c <std::vector<int>::push_back(int&&)+0x1c>
+            0000000000000040 <A::operator<(A const&) const>:
+              40:\tcall   0 <std::vector<int>::push_back(int&&)>
+              45:\tcall   <foo(int, char)>
+        "};
 
-        let result = Disasm::try_from(lines);
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
 
+        let section = disasm.section_by_name(".text").unwrap();
+        let symbols = section.symbols().collect::<Vec<_>>();
+        assert_eq!(symbols[0].get_name(), "<A::operator<(A const&) const>");
         assert_eq!(
-            result,
-            Err("Attempted to add a symbol without first defining a section".to_string())
-        )
+            symbols[1].get_name(),
+            "<std::vector<int>::push_back(int&&)>"
+        );
+        assert_eq!(symbols[1].get_address(), Some(0));
+        assert_eq!(
+            symbols[1].instructions().next().unwrap().target(),
+            Some(BranchTarget::new(
+                "std::vector<int>::push_back(int&&)",
+                Some(0x1c)
+            ))
+        );
+        assert_eq!(
+            disasm.call_edges(),
+            [
+                (
+                    "<A::operator<(A const&) const>".to_string(),
+                    "<std::vector<int>::push_back(int&&)>".to_string()
+                ),
+                (
+                    "<A::operator<(A const&) const>".to_string(),
+                    "<foo(int, char)>".to_string()
+                )
+            ]
+        );
     }
 
     #[test]
-    fn try_from_instruction_before_section_fails() {
-        let lines = indoc! {r"
-            folder\file:     file format some_format
+    fn from_lines_section_flags_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
 
-            	opc1
-        "}
-        .to_string();
+            Sections:
+            Idx Name          Size      VMA               LMA               File off  Algn
+              0 .text         00000001  0000000000000000  0000000000000000  00000040  2**0
+                              CONTENTS, ALLOC, LOAD, READONLY, CODE
+              1 .data         00000000  0000000000000000  0000000000000000  00000041  2**0
+                              CONTENTS, ALLOC, LOAD, DATA
 
-        let result = Disasm::try_from(lines);
+            Disassembly of section .text:
+            <main>:
+            \tret
+        "};
 
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+
+        let text = disasm.section_by_name(".text").unwrap();
         assert_eq!(
-            result,
-            Err("Attempted to add an instruction without first defining a section".to_string())
-        )
+            text.flags(),
+            ["CONTENTS", "ALLOC", "LOAD", "READONLY", "CODE"]
+        );
+        assert_eq!(disasm.len(), 1);
     }
 
     #[test]
-    fn from_lines_sorting_sections_and_symbols_ok() {
-        let lines = indoc! {r"
-            folder\file:     file format some_format
-            Disassembly of section abb:
-            <zsym1>:
-            <asym2>:
-            <bsym4>:
-            <bsym3>:
-            Disassembly of section aaa:
-            <sym3>:
-            <sym1>:
-            Disassembly of section adc:
-            Disassembly of section abc:
-            Disassembly of section acc:
-        "}
-        .to_string();
+    fn from_lines_section_flags_llvm_ok() {
+        let lines = indoc! {"
+            file.o:\tfile format elf64-x86-64
 
-        let result = Disasm::try_from(lines);
+            Sections:
+            Idx Name          Size     VMA              Type
+              0               00000000 0000000000000000
+              1 .text         00000001 0000000000000000 TEXT
+              2 .bss          00000000 0000000000000000 BSS
 
-        let mut sec1 = Section::new("aaa");
-        sec1.add_symbol(Symbol::new("<sym1>"));
-        sec1.add_symbol(Symbol::new("<sym3>"));
-        let mut sec2 = Section::new("abb");
-        sec2.add_symbol(Symbol::new("<asym2>"));
-        sec2.add_symbol(Symbol::new("<bsym3>"));
-        sec2.add_symbol(Symbol::new("<bsym4>"));
-        sec2.add_symbol(Symbol::new("<zsym1>"));
-        let sec3 = Section::new("abc");
-        let sec4 = Section::new("acc");
-        let sec5 = Section::new("adc");
+            Disassembly of section .text:
+            <main>:
+            \tret
+        "};
 
-        assert_eq!(
-            result,
-            Ok(Disasm {
-                file_name: "folder\\file".to_string(),
-                file_format: "some_format".to_string(),
-                sections: Vec::from([sec1, sec2, sec3, sec4, sec5]),
-            })
-        )
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+
+        assert_eq!(disasm.section_by_name(".text").unwrap().flags(), ["TEXT"]);
     }
 
     #[test]
-    fn to_string_ok() {
-        let mut sec1 = Section::new("aaa");
-        sec1.add_symbol(Symbol::new("<sym1>"));
-        sec1.add_symbol(Symbol::new("<sym3>"));
-        let _ = sec1.add_instruction(Instruction::new("opc5", "", ""));
-        let _ = sec1.add_instruction(Instruction::new("opc3", "", ""));
-        let sec2 = Section::new("abc");
-        let mut sec3 = Section::new("abb");
-        sec3.add_symbol(Symbol::new("<zsym2>"));
-        let _ = sec3.add_instruction(Instruction::new("opc1", "", ""));
-        let _ = sec3.add_instruction(Instruction::new("opc2", "opr1,opr2", ""));
-        let _ = sec3.add_instruction(Instruction::new("opc4", "opr3", "comment1"));
-        sec3.add_symbol(Symbol::new("<asym1>"));
-        let disasm = Disasm {
-            file_name: "folder\\file".to_string(),
-            file_format: "some_format".to_string(),
-            sections: Vec::from([sec1, sec2, sec3]),
-        };
+    fn from_lines_without_section_flags_ok() {
+        let lines = indoc! {"
+            file.o:     file format elf64-x86-64
+            Disassembly of section .text:
+            <main>:
+            \tret
+        "};
+
+        let disasm = Disasm::from_lines(lines.lines(), &ParseOptions::default()).unwrap();
+
+        assert!(disasm.section_by_name(".text").unwrap().flags().is_empty());
+    }
+
+    #[test]
+    fn serialize_json_ok() {
+        let lines = indoc! {"
+            folder\\file:     file format some_format
+            Disassembly of section sec1:
+            <sym1>:
+                opc1
+                opc2    %opr1,%opr2      # comment1
+        "}
+        .to_string();
+
+        let disasm = Disasm::try_from(lines).unwrap();
 
         assert_eq!(
-            disasm.to_string(),
-            indoc! {"
-                aaa:
-                    <sym1>:
-                    <sym3>:
-                        opc5
-                        opc3
-                abc:
-                abb:
-                    <zsym2>:
-                        opc1
-                        opc2
-                        opc4
-                    <asym1>:
-            "}
-            .to_string()
+            serde_json::to_value(&disasm).unwrap(),
+            serde_json::json!({
+                "file_name": "folder\\file",
+                "file_format": "some_format",
+                "sections": [{
+                    "name": "sec1",
+                    "flags": [],
+                    "symbols": [{
+                        "name": "<sym1>",
+                        "address": null,
+                        "byte_size": null,
+                        "instructions": [
+                            {
+                                "kind": "opcode",
+                                "address": null,
+                                "raw_bytes": null,
+                                "opcode": "opc1",
+                                "operands": "",
+                                "comment": "",
+                                "reloc": null,
+                            },
+                            {
+                                "kind": "opcode",
+                                "address": null,
+                                "raw_bytes": null,
+                                "opcode": "opc2",
+                                "operands": "%opr1,%opr2",
+                                "comment": "comment1",
+                                "reloc": null,
+                            },
+                        ],
+                    }],
+                }],
+            })
         )
     }
 }
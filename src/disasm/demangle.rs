@@ -0,0 +1,132 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Demangling of symbol names.
+//!
+//! This module contains the functions used to convert mangled symbol names into their human readable form.
+
+/// Demangling scheme applied to the symbol names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Demangler {
+    /// Rust symbols, both in the legacy and in the v0 mangling scheme.
+    Rust,
+    /// C++ symbols mangled according to the Itanium ABI.
+    Cpp,
+    /// Try the rust demangler first, then the C++ one.
+    Auto,
+}
+
+fn demangle_rust(name: &str) -> Option<String> {
+    rustc_demangle::try_demangle(name)
+        .ok()
+        .map(|demangled| format!("{demangled:#}"))
+}
+
+fn demangle_cpp(name: &str) -> Option<String> {
+    if !name.starts_with("_Z") {
+        return None;
+    }
+    cpp_demangle::Symbol::new(name)
+        .ok()
+        .map(|symbol| symbol.to_string())
+}
+
+/// Demangle a symbol name in the `<name>` or `<name@suffix>` form.
+///
+/// Names that are not mangled, or that can't be demangled with the chosen scheme, are returned unchanged.
+pub fn demangle(sym_name: &str, demangler: Demangler) -> String {
+    let inner = sym_name
+        .strip_prefix('<')
+        .and_then(|name| name.strip_suffix('>'))
+        .unwrap_or(sym_name);
+    let (name, suffix) = match inner.split_once('@') {
+        Some((name, suffix)) => (name, format!("@{suffix}")),
+        None => (inner, String::from("")),
+    };
+    let demangled = match demangler {
+        Demangler::Rust => demangle_rust(name),
+        Demangler::Cpp => demangle_cpp(name),
+        Demangler::Auto => demangle_rust(name).or_else(|| demangle_cpp(name)),
+    };
+    match demangled {
+        Some(demangled) => sym_name.replacen(inner, &format!("{demangled}{suffix}"), 1),
+        None => sym_name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn demangle_rust_symbol_ok() {
+        assert_eq!(
+            demangle(
+                "<_ZN4core3fmt9Formatter3pad17h0123456789abcdefE>",
+                Demangler::Rust
+            ),
+            "<core::fmt::Formatter::pad>".to_string()
+        )
+    }
+
+    #[test]
+    fn demangle_rust_symbol_with_suffix_ok() {
+        assert_eq!(
+            demangle(
+                "<_ZN4core3fmt9Formatter3pad17h0123456789abcdefE@plt>",
+                Demangler::Auto
+            ),
+            "<core::fmt::Formatter::pad@plt>".to_string()
+        )
+    }
+
+    #[test]
+    fn demangle_not_mangled_symbol_ok() {
+        assert_eq!(demangle("<main>", Demangler::Auto), "<main>".to_string());
+        assert_eq!(
+            demangle("<printf@plt>", Demangler::Auto),
+            "<printf@plt>".to_string()
+        )
+    }
+
+    #[test]
+    fn demangle_cpp_symbol_ok() {
+        assert_eq!(
+            demangle("<_ZNSt6vectorIiSaIiEE9push_backEOi>", Demangler::Cpp),
+            "<std::vector<int, std::allocator<int> >::push_back(int&&)>".to_string()
+        );
+        assert_eq!(
+            demangle("<_Z3fooi@plt>", Demangler::Auto),
+            "<foo(int)@plt>".to_string()
+        )
+    }
+
+    #[test]
+    fn demangle_wrong_scheme_ok() {
+        assert_eq!(
+            demangle("<_Z3fooi>", Demangler::Rust),
+            "<_Z3fooi>".to_string()
+        );
+        assert_eq!(
+            demangle("<_RNvCs1234_7mycrate3foo>", Demangler::Cpp),
+            "<_RNvCs1234_7mycrate3foo>".to_string()
+        )
+    }
+}
@@ -0,0 +1,164 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the DiffReport struct.
+//!
+//! This module contains the DiffReport struct which lists the symbol-level changes between two disassemblies.
+use std::fmt;
+
+use serde::Serialize;
+
+/// Symbols added, removed or changed in a single section.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SectionDiff {
+    name: String,
+    added: Vec<String>,
+    removed: Vec<String>,
+    changed: Vec<String>,
+}
+
+impl SectionDiff {
+    pub fn new(name: &str) -> Self {
+        SectionDiff {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn add_added(&mut self, symbol: &str) {
+        self.added.push(symbol.to_string());
+    }
+
+    pub fn add_removed(&mut self, symbol: &str) {
+        self.removed.push(symbol.to_string());
+    }
+
+    pub fn add_changed(&mut self, symbol: &str) {
+        self.changed.push(symbol.to_string());
+    }
+
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Symbols only present in the new disassembly.
+    pub fn added(&self) -> &[String] {
+        &self.added
+    }
+
+    /// Symbols only present in the old disassembly.
+    pub fn removed(&self) -> &[String] {
+        &self.removed
+    }
+
+    /// Symbols present in both disassemblies with a different sequence of opcodes.
+    pub fn changed(&self) -> &[String] {
+        &self.changed
+    }
+
+    /// Check if the section contains no changes.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for SectionDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}:", self.name)?;
+        for symbol in &self.added {
+            writeln!(f, "    + {symbol}")?;
+        }
+        for symbol in &self.removed {
+            writeln!(f, "    - {symbol}")?;
+        }
+        for symbol in &self.changed {
+            writeln!(f, "    ~ {symbol}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Symbol-level changes between two disassemblies, grouped by section.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DiffReport {
+    sections: Vec<SectionDiff>,
+}
+
+impl DiffReport {
+    pub fn new() -> Self {
+        DiffReport::default()
+    }
+
+    /// Add the changes of a section, sections without changes are ignored.
+    pub fn add_section(&mut self, section: SectionDiff) {
+        if !section.is_empty() {
+            self.sections.push(section);
+        }
+    }
+
+    /// Iterate over the sections containing at least one change.
+    pub fn sections(&self) -> impl Iterator<Item = &SectionDiff> {
+        self.sections.iter()
+    }
+
+    /// Check if the two disassemblies have no symbol-level differences.
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for section in &self.sections {
+            write!(f, "{section}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_section_without_changes_ignored_ok() {
+        let mut report = DiffReport::new();
+        report.add_section(SectionDiff::new(".text"));
+        assert!(report.is_empty());
+        assert_eq!(report.to_string(), "");
+    }
+
+    #[test]
+    fn to_string_ok() {
+        let mut text = SectionDiff::new(".text");
+        text.add_added("<new>");
+        text.add_removed("<old>");
+        text.add_changed("<main>");
+        let mut init = SectionDiff::new(".init");
+        init.add_changed("<_init>");
+        let mut report = DiffReport::new();
+        report.add_section(init);
+        report.add_section(text);
+        assert_eq!(
+            report.to_string(),
+            ".init:\n    ~ <_init>\n.text:\n    + <new>\n    - <old>\n    ~ <main>\n"
+        );
+    }
+}
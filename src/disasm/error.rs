@@ -0,0 +1,172 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the DisasmError enum.
+//!
+//! This module contains the DisasmError enum which lists the ways parsing and exporting a disassembly can fail.
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::num::ParseIntError;
+
+/// Error returned when parsing a disassembly or exporting it fails.
+///
+/// Errors caused by a line of the input are wrapped in [`DisasmError::Line`] with the number of that line.
+#[derive(Debug)]
+pub enum DisasmError {
+    /// The input does not contain any text.
+    EmptyInput,
+    /// No line contains the file name and format, reported on the first line.
+    BadFirstLine,
+    /// A line matching no known format, outside of the lenient mode.
+    UnrecognizedLine(String),
+    /// A symbol name not made of a single balanced `<...>` pair.
+    MalformedSymbolName(String),
+    /// A symbol preceding the first section.
+    SymbolBeforeSection,
+    /// An instruction preceding the first section.
+    InstructionBeforeSection,
+    /// An instruction preceding the first symbol of its section.
+    InstructionBeforeSymbol,
+    /// A relocation preceding the first instruction.
+    RelocationBeforeInstruction,
+    /// A standalone comment preceding the first instruction.
+    CommentBeforeInstruction,
+    /// A line of raw bytes preceding the first instruction.
+    RawBytesBeforeInstruction,
+    /// An address or raw byte that is not a valid hex number.
+    InvalidNumber(ParseIntError),
+    /// Failure reading the input or writing the output.
+    Io(io::Error),
+    /// Failure writing the CSV output.
+    Csv(csv::Error),
+    /// Error caused by the line `line_no` of the input, counting from one.
+    Line {
+        line_no: usize,
+        error: Box<DisasmError>,
+    },
+}
+
+impl DisasmError {
+    /// Attach the number of the line causing the error.
+    pub(crate) fn at_line(self, line_no: usize) -> Self {
+        DisasmError::Line {
+            line_no,
+            error: Box::new(self),
+        }
+    }
+}
+
+impl fmt::Display for DisasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisasmError::EmptyInput => write!(f, "Error, the file does not contain any text"),
+            DisasmError::BadFirstLine => write!(f, "Incorrect format for the first line"),
+            DisasmError::UnrecognizedLine(line) => {
+                write!(f, "Unrecognized format for the following line: '{line}'")
+            }
+            DisasmError::MalformedSymbolName(name) => {
+                write!(f, "Mismatched angle brackets in the symbol name '{name}'")
+            }
+            DisasmError::SymbolBeforeSection => {
+                write!(
+                    f,
+                    "Attempted to add a symbol without first defining a section"
+                )
+            }
+            DisasmError::InstructionBeforeSection => write!(
+                f,
+                "Attempted to add an instruction without first defining a section"
+            ),
+            DisasmError::InstructionBeforeSymbol => write!(
+                f,
+                "Attempted to add an instruction without first defining a symbol"
+            ),
+            DisasmError::RelocationBeforeInstruction => write!(
+                f,
+                "Attempted to add a relocation without first defining an instruction"
+            ),
+            DisasmError::CommentBeforeInstruction => write!(
+                f,
+                "Attempted to add a comment without first defining an instruction"
+            ),
+            DisasmError::RawBytesBeforeInstruction => write!(
+                f,
+                "Attempted to add raw bytes without first defining an instruction"
+            ),
+            DisasmError::InvalidNumber(error) => error.fmt(f),
+            DisasmError::Io(error) => error.fmt(f),
+            DisasmError::Csv(error) => error.fmt(f),
+            DisasmError::Line { line_no, error } => write!(f, "Error on line {line_no}: {error}"),
+        }
+    }
+}
+
+impl Error for DisasmError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DisasmError::InvalidNumber(error) => Some(error),
+            DisasmError::Io(error) => Some(error),
+            DisasmError::Csv(error) => Some(error),
+            DisasmError::Line { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseIntError> for DisasmError {
+    fn from(error: ParseIntError) -> Self {
+        DisasmError::InvalidNumber(error)
+    }
+}
+
+impl From<io::Error> for DisasmError {
+    fn from(error: io::Error) -> Self {
+        DisasmError::Io(error)
+    }
+}
+
+impl From<csv::Error> for DisasmError {
+    fn from(error: csv::Error) -> Self {
+        DisasmError::Csv(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_string_at_line_ok() {
+        let error = DisasmError::UnrecognizedLine("gibberish".to_string()).at_line(3);
+        assert_eq!(
+            error.to_string(),
+            "Error on line 3: Unrecognized format for the following line: 'gibberish'"
+        );
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn from_parse_int_error_ok() {
+        let error = DisasmError::from(u8::from_str_radix("zz", 16).unwrap_err());
+        assert!(matches!(error, DisasmError::InvalidNumber(_)));
+        assert_eq!(error.to_string(), "invalid digit found in string");
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the FileFormat enum.
+//!
+//! This module contains the FileFormat enum which is the parsed form of the file format printed by objdump.
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// Object file format and target architecture, such as `elf64-x86-64`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileFormat {
+    Elf {
+        bits: u32,
+        architecture: String,
+    },
+    MachO {
+        architecture: String,
+    },
+    Pe {
+        architecture: String,
+    },
+    /// Any format not recognized, stored as printed by objdump.
+    Other(String),
+}
+
+impl FileFormat {
+    /// Size in bits of the addresses, if known.
+    pub fn bitness(&self) -> Option<u32> {
+        match self {
+            FileFormat::Elf { bits, .. } => Some(*bits),
+            FileFormat::MachO { architecture } | FileFormat::Pe { architecture } => {
+                match architecture.as_str() {
+                    "x86-64" | "aarch64" | "arm64" => Some(64),
+                    "i386" | "arm" => Some(32),
+                    _ => None,
+                }
+            }
+            FileFormat::Other(_) => None,
+        }
+    }
+
+    /// Target architecture, without the endianness, if known.
+    pub fn architecture(&self) -> Option<&str> {
+        match self {
+            FileFormat::Elf { architecture, .. }
+            | FileFormat::MachO { architecture }
+            | FileFormat::Pe { architecture } => Some(architecture),
+            FileFormat::Other(_) => None,
+        }
+    }
+}
+
+impl From<&str> for FileFormat {
+    fn from(format: &str) -> Self {
+        lazy_static! {
+            static ref RE_ELF: Regex =
+                Regex::new(r"^elf(?P<bits>32|64)-(little|big)?(?P<arch>[[:alnum:]_-]+)$").unwrap();
+            // GNU objdump prints mach-o-x86-64, llvm-objdump prints Mach-O 64-bit x86-64
+            static ref RE_MACH_O: Regex =
+                Regex::new(r"^(?i:mach-o)[- ]((32|64)-bit )?(?P<arch>[[:alnum:]_-]+)$").unwrap();
+            // GNU objdump prints pe-i386 or pei-x86-64, llvm-objdump prints COFF-x86-64
+            static ref RE_PE: Regex =
+                Regex::new(r"^(pei?|COFF)-(?P<arch>[[:alnum:]_-]+)$").unwrap();
+        }
+        if let Some(cap) = RE_ELF.captures(format) {
+            FileFormat::Elf {
+                bits: if &cap["bits"] == "32" { 32 } else { 64 },
+                architecture: cap["arch"].to_string(),
+            }
+        } else if let Some(cap) = RE_MACH_O.captures(format) {
+            FileFormat::MachO {
+                architecture: cap["arch"].to_string(),
+            }
+        } else if let Some(cap) = RE_PE.captures(format) {
+            FileFormat::Pe {
+                architecture: cap["arch"].to_string(),
+            }
+        } else {
+            FileFormat::Other(format.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_elf64_x86_64_ok() {
+        let format = FileFormat::from("elf64-x86-64");
+        assert_eq!(
+            format,
+            FileFormat::Elf {
+                bits: 64,
+                architecture: "x86-64".to_string()
+            }
+        );
+        assert_eq!(format.bitness(), Some(64));
+        assert_eq!(format.architecture(), Some("x86-64"));
+    }
+
+    #[test]
+    fn from_elf32_littlearm_ok() {
+        let format = FileFormat::from("elf32-littlearm");
+        assert_eq!(format.bitness(), Some(32));
+        assert_eq!(format.architecture(), Some("arm"));
+    }
+
+    #[test]
+    fn from_mach_o_and_pe_ok() {
+        for (text, architecture) in [
+            ("mach-o-x86-64", "x86-64"),
+            ("Mach-O 64-bit x86-64", "x86-64"),
+            ("Mach-O arm64", "arm64"),
+        ] {
+            let format = FileFormat::from(text);
+            assert!(matches!(format, FileFormat::MachO { .. }), "{text}");
+            assert_eq!(format.architecture(), Some(architecture));
+            assert_eq!(format.bitness(), Some(64));
+        }
+        for text in ["pe-i386", "pei-i386", "COFF-i386"] {
+            let format = FileFormat::from(text);
+            assert!(matches!(format, FileFormat::Pe { .. }), "{text}");
+            assert_eq!(format.architecture(), Some("i386"));
+            assert_eq!(format.bitness(), Some(32));
+        }
+    }
+
+    #[test]
+    fn from_unknown_format_ok() {
+        let format = FileFormat::from("some_format");
+        assert_eq!(format, FileFormat::Other("some_format".to_string()));
+        assert_eq!(format.bitness(), None);
+        assert_eq!(format.architecture(), None);
+    }
+}
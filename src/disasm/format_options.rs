@@ -0,0 +1,69 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the FormatOptions struct.
+//!
+//! This module contains the FormatOptions struct which controls how the parsed disassembly is printed.
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// Print the operands after the opcode of each instruction.
+    pub operands: bool,
+    /// Print the comment after each instruction, when present.
+    pub comments: bool,
+    /// String used to indent each nesting level, four spaces by default.
+    pub indent: String,
+    /// Print the relocations of each instruction on the following line, one level deeper.
+    pub relocations: bool,
+    /// Elide the operands longer than this number of characters, ending them with `…`.
+    pub max_operand_width: Option<usize>,
+    /// Print runs of at least this many identical instructions once, followed by `(x N)`.
+    pub collapse_repeats: Option<usize>,
+    /// Pad the opcodes to the longest one of their symbol, aligning the operands into a column.
+    pub align: bool,
+    /// Print the flags of each section after its name, when they are known.
+    pub section_flags: bool,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            operands: false,
+            comments: false,
+            indent: "    ".to_string(),
+            relocations: false,
+            max_operand_width: None,
+            collapse_repeats: None,
+            align: false,
+            section_flags: false,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Write the indentation for the nesting level `depth`.
+    pub(crate) fn write_indent<W: fmt::Write>(&self, out: &mut W, depth: usize) -> fmt::Result {
+        for _ in 0..depth {
+            out.write_str(&self.indent)?;
+        }
+        Ok(())
+    }
+}
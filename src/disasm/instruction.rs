@@ -22,28 +22,570 @@
 //!
 //! This module contains the Instruction struct which can parse a string containing an instruction and store in
 //! its components.
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt;
 
-#[derive(Debug, PartialEq, Eq)]
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Serialize;
+
+use super::FormatOptions;
+
+/// Mnemonics spelled differently by some objdump versions, each with its canonical form.
+const CANONICAL_MNEMONICS: &[(&str, &str)] = &[
+    ("callq", "call"),
+    ("jmpq", "jmp"),
+    ("leaveq", "leave"),
+    ("movabs", "mov"),
+    ("retq", "ret"),
+];
+
+/// Mnemonics with a fixed category, matched after stripping the AT&T size suffix if needed.
+///
+/// The classification only covers common x86 mnemonics for now.
+const CATEGORY_MNEMONICS: &[(&str, OpcodeCategory)] = &[
+    ("call", OpcodeCategory::Branch),
+    ("ret", OpcodeCategory::Branch),
+    ("iret", OpcodeCategory::Branch),
+    ("loop", OpcodeCategory::Branch),
+    ("loope", OpcodeCategory::Branch),
+    ("loopne", OpcodeCategory::Branch),
+    ("add", OpcodeCategory::Arithmetic),
+    ("adc", OpcodeCategory::Arithmetic),
+    ("sub", OpcodeCategory::Arithmetic),
+    ("sbb", OpcodeCategory::Arithmetic),
+    ("inc", OpcodeCategory::Arithmetic),
+    ("dec", OpcodeCategory::Arithmetic),
+    ("neg", OpcodeCategory::Arithmetic),
+    ("mul", OpcodeCategory::Arithmetic),
+    ("imul", OpcodeCategory::Arithmetic),
+    ("div", OpcodeCategory::Arithmetic),
+    ("idiv", OpcodeCategory::Arithmetic),
+    ("and", OpcodeCategory::Arithmetic),
+    ("or", OpcodeCategory::Arithmetic),
+    ("xor", OpcodeCategory::Arithmetic),
+    ("not", OpcodeCategory::Arithmetic),
+    ("shl", OpcodeCategory::Arithmetic),
+    ("shr", OpcodeCategory::Arithmetic),
+    ("sal", OpcodeCategory::Arithmetic),
+    ("sar", OpcodeCategory::Arithmetic),
+    ("rol", OpcodeCategory::Arithmetic),
+    ("ror", OpcodeCategory::Arithmetic),
+    ("cmp", OpcodeCategory::Arithmetic),
+    ("test", OpcodeCategory::Arithmetic),
+    ("lea", OpcodeCategory::Arithmetic),
+    ("xchg", OpcodeCategory::Memory),
+    ("cmpxchg", OpcodeCategory::Memory),
+    ("stos", OpcodeCategory::Memory),
+    ("lods", OpcodeCategory::Memory),
+    ("push", OpcodeCategory::Stack),
+    ("pop", OpcodeCategory::Stack),
+    ("pushf", OpcodeCategory::Stack),
+    ("popf", OpcodeCategory::Stack),
+    ("enter", OpcodeCategory::Stack),
+    ("leave", OpcodeCategory::Stack),
+    ("syscall", OpcodeCategory::Syscall),
+    ("sysenter", OpcodeCategory::Syscall),
+    ("sysret", OpcodeCategory::Syscall),
+    ("int", OpcodeCategory::Syscall),
+    ("nop", OpcodeCategory::Nop),
+    ("fnop", OpcodeCategory::Nop),
+];
+
+/// Prefixes shared by families of mnemonics, such as the conditional jumps or the `mov` variants.
+const CATEGORY_PREFIXES: &[(&str, OpcodeCategory)] = &[
+    ("j", OpcodeCategory::Branch),
+    ("mov", OpcodeCategory::Memory),
+    ("cmov", OpcodeCategory::Memory),
+];
+
+/// Coarse category of an instruction, based on its mnemonic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OpcodeCategory {
+    /// Jumps, calls, returns and loops.
+    Branch,
+    /// Integer arithmetic, logic, shifts and comparisons, while floating point and vector arithmetic counts as
+    /// [`OpcodeCategory::Other`].
+    Arithmetic,
+    /// Data moves between registers and memory.
+    Memory,
+    /// Pushes, pops and stack frame setup.
+    Stack,
+    /// System calls and software interrupts.
+    Syscall,
+    /// No operation.
+    Nop,
+    /// Any other instruction, directive or skipped line.
+    Other,
+}
+
+impl fmt::Display for OpcodeCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OpcodeCategory::Branch => "branch",
+            OpcodeCategory::Arithmetic => "arithmetic",
+            OpcodeCategory::Memory => "memory",
+            OpcodeCategory::Stack => "stack",
+            OpcodeCategory::Syscall => "syscall",
+            OpcodeCategory::Nop => "nop",
+            OpcodeCategory::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Kind of a line stored in a symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InstructionKind {
+    /// A regular instruction, made of an opcode and optional operands and comment.
+    Opcode,
+    /// A '...' line, used by objdump to collapse a sequence of repeated zero bytes.
+    Skip,
+    /// An assembler directive, such as `.word 0x1234`, used by objdump for data in code sections.
+    Directive,
+}
+
+/// Target referenced by the operands of a branch, such as `<main+0x24>` or a bare `0x4011a0`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BranchTarget {
+    /// Symbol-relative target, with the name of the symbol and the offset from its start.
+    Symbol { symbol: String, offset: Option<u64> },
+    /// Bare address not associated with any symbol.
+    Address(u64),
+}
+
+impl BranchTarget {
+    /// Symbol-relative target.
+    pub fn new(symbol: &str, offset: Option<u64>) -> Self {
+        BranchTarget::Symbol {
+            symbol: symbol.to_string(),
+            offset,
+        }
+    }
+
+    /// Name of the target symbol, without angle brackets, if any.
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            BranchTarget::Symbol { symbol, .. } => Some(symbol),
+            BranchTarget::Address(_) => None,
+        }
+    }
+
+    /// Offset from the start of the target symbol, if any.
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            BranchTarget::Symbol { offset, .. } => *offset,
+            BranchTarget::Address(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct Instruction {
+    kind: InstructionKind,
+    address: Option<u64>,
+    raw_bytes: Option<Vec<u8>>,
     opcode: String,
     operands: String,
     comment: String,
+    reloc: Option<String>,
+}
+
+/// Instructions are ordered by opcode, then operands, then comment.
+///
+/// The remaining fields only break the ties, to keep the ordering consistent with equality.
+impl Ord for Instruction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.opcode
+            .cmp(&other.opcode)
+            .then_with(|| self.operands.cmp(&other.operands))
+            .then_with(|| self.comment.cmp(&other.comment))
+            .then_with(|| self.kind.cmp(&other.kind))
+            .then_with(|| self.address.cmp(&other.address))
+            .then_with(|| self.raw_bytes.cmp(&other.raw_bytes))
+            .then_with(|| self.reloc.cmp(&other.reloc))
+    }
+}
+
+impl PartialOrd for Instruction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Instruction {
     pub fn new(opcode: &str, operands: &str, comment: &str) -> Self {
         Instruction {
+            kind: InstructionKind::Opcode,
+            address: None,
+            raw_bytes: None,
             opcode: opcode.to_string(),
             operands: operands.to_string(),
             comment: comment.to_string(),
+            reloc: None,
+        }
+    }
+
+    pub fn new_skip() -> Self {
+        Instruction {
+            kind: InstructionKind::Skip,
+            address: None,
+            raw_bytes: None,
+            opcode: String::from(""),
+            operands: String::from(""),
+            comment: String::from(""),
+            reloc: None,
+        }
+    }
+
+    /// Create a directive, storing its arguments as operands.
+    pub fn new_directive(directive: &str, arguments: &str) -> Self {
+        Instruction {
+            kind: InstructionKind::Directive,
+            opcode: directive.to_string(),
+            operands: arguments.to_string(),
+            ..Instruction::new_skip()
+        }
+    }
+
+    pub fn kind(&self) -> InstructionKind {
+        self.kind
+    }
+
+    pub fn opcode(&self) -> &str {
+        &self.opcode
+    }
+
+    pub fn operands(&self) -> &str {
+        &self.operands
+    }
+
+    pub fn comment(&self) -> &str {
+        &self.comment
+    }
+
+    /// Parse the `<name>` or `<name+0xNN>` token at the end of the operands, if present.
+    ///
+    /// Jumps, calls and loops whose only operand is an address, such as `jmp 0x4011a0`, target that address.
+    pub fn target(&self) -> Option<BranchTarget> {
+        lazy_static! {
+            static ref RE_TARGET: Regex =
+                Regex::new(r"<(?P<symbol>.+?)(\+0x(?P<offset>[[:xdigit:]]+))?>$").unwrap();
+            static ref RE_ADDRESS_TARGET: Regex =
+                Regex::new(r"^(0x)?(?P<address>[[:xdigit:]]{1,16})$").unwrap();
+        }
+        let operands = self.operands.trim();
+        if let Some(target_cap) = RE_TARGET.captures(operands) {
+            let offset = match target_cap.name("offset") {
+                Some(offset) => Some(u64::from_str_radix(offset.as_str(), 16).ok()?),
+                None => None,
+            };
+            return Some(BranchTarget::new(&target_cap["symbol"], offset));
         }
+        let address_cap = RE_ADDRESS_TARGET
+            .captures(operands)
+            .filter(|_| self.is_branch())?;
+        let address = u64::from_str_radix(&address_cap["address"], 16).ok()?;
+        Some(BranchTarget::Address(address))
+    }
+
+    /// Whether the mnemonic, without prefixes, is a jump, call or loop.
+    fn is_branch(&self) -> bool {
+        let mnemonic = self.opcode.rsplit(' ').next().unwrap_or_default();
+        ["j", "call", "loop"]
+            .iter()
+            .any(|prefix| mnemonic.starts_with(prefix))
     }
+
+    /// Category of the instruction, looked up from its mnemonic without prefixes such as `lock` or `rep`.
+    ///
+    /// Only x86 mnemonics are classified, anything else falls in [`OpcodeCategory::Other`].
+    pub fn category(&self) -> OpcodeCategory {
+        if self.kind != InstructionKind::Opcode {
+            return OpcodeCategory::Other;
+        }
+        let mnemonic = self.opcode.rsplit(' ').next().unwrap_or_default();
+        let unsuffixed = mnemonic
+            .strip_suffix(['b', 'w', 'l', 'q'])
+            .unwrap_or(mnemonic);
+        let exact = CATEGORY_MNEMONICS
+            .iter()
+            .find(|(name, _)| *name == mnemonic || *name == unsuffixed);
+        let prefixed = || {
+            CATEGORY_PREFIXES
+                .iter()
+                .find(|(prefix, _)| mnemonic.starts_with(prefix))
+        };
+        exact
+            .or_else(prefixed)
+            .map_or(OpcodeCategory::Other, |(_, category)| *category)
+    }
+
+    /// Number of operands, counting the commas outside of parentheses, brackets and `<symbol>` targets.
+    ///
+    /// Memory operands such as `(%rbp,%rax,4)` or `[rbp+rax*4]` count as a single operand.
+    pub fn operand_count(&self) -> usize {
+        if self.operands.trim().is_empty() {
+            return 0;
+        }
+        let mut depth = 0usize;
+        let mut count = 1;
+        for c in self.operands.chars() {
+            match c {
+                '(' | '[' | '{' | '<' => depth += 1,
+                ')' | ']' | '}' | '>' => depth = depth.saturating_sub(1),
+                ',' if depth == 0 => count += 1,
+                _ => (),
+            }
+        }
+        count
+    }
+
+    /// Operands with the hex addresses replaced by the `0xADDR` placeholder.
+    ///
+    /// Only the literals that depend on where the code is linked are replaced: the address and offset of a
+    /// `<symbol+0xNN>` target, the address operand of a jump, call or loop, and RIP-relative displacements such as
+    /// `0x2fe2(%rip)` or `[rip+0x2fe2]`. Immediates, stack offsets such as `-0x8(%rbp)` and register names are left
+    /// untouched.
+    pub fn normalized_operands(&self) -> String {
+        lazy_static! {
+            static ref RE_SYMBOL_ADDRESS: Regex = Regex::new(
+                r"(?P<before>^|[[:space:],*])(0x)?[[:xdigit:]]+(?P<after>[[:space:]]+<)"
+            )
+            .unwrap();
+            static ref RE_SYMBOL_OFFSET: Regex =
+                Regex::new(r"<(?P<symbol>[^<>]+?)\+0x[[:xdigit:]]+>").unwrap();
+            static ref RE_RIP_RELATIVE: Regex = Regex::new(
+                r"(-?0x[[:xdigit:]]+(?P<att>\(%rip\))|(?P<intel>\brip[+-])0x[[:xdigit:]]+)"
+            )
+            .unwrap();
+            static ref RE_BRANCH_ADDRESS: Regex =
+                Regex::new(r"^(?P<indirect>\*?)(0x)?[[:xdigit:]]+$").unwrap();
+        }
+        let operands = RE_SYMBOL_ADDRESS.replace_all(&self.operands, "${before}0xADDR${after}");
+        let operands = RE_SYMBOL_OFFSET.replace_all(&operands, "<${symbol}+0xADDR>");
+        let operands = RE_RIP_RELATIVE.replace_all(&operands, "${intel}0xADDR${att}");
+        if self.is_branch() {
+            RE_BRANCH_ADDRESS
+                .replace(operands.trim(), "${indirect}0xADDR")
+                .into_owned()
+        } else {
+            operands.into_owned()
+        }
+    }
+
+    /// Replace the hex addresses in the operands and comment with the `0xADDR` placeholder.
+    ///
+    /// The operands are normalized as in [`Instruction::normalized_operands`], while every hex literal of the comment
+    /// is replaced, as objdump only uses it for resolved addresses such as `# 3ff0 <_DYNAMIC+0x1e0>`.
+    pub fn normalize_addresses(&mut self) {
+        self.operands = self.normalized_operands();
+        self.comment = normalize_hex(&self.comment);
+    }
+
+    /// Replace the mnemonic of the opcode, keeping its prefixes, with its canonical form when it has one.
+    pub fn canonicalize_opcode(&mut self) {
+        let (prefixes, mnemonic) = match self.opcode.rsplit_once(' ') {
+            Some((prefixes, mnemonic)) => (Some(prefixes), mnemonic),
+            None => (None, self.opcode.as_str()),
+        };
+        let canonical = CANONICAL_MNEMONICS
+            .iter()
+            .find(|(variant, _)| *variant == mnemonic)
+            .map(|(_, canonical)| canonical);
+        if let Some(canonical) = canonical {
+            self.opcode = match prefixes {
+                Some(prefixes) => format!("{prefixes} {canonical}"),
+                None => canonical.to_string(),
+            };
+        }
+    }
+
+    /// Append `operands` to the operands of the instruction, separating them with a space.
+    pub fn append_operands(&mut self, operands: &str) {
+        if !self.operands.is_empty() {
+            self.operands.push(' ');
+        }
+        self.operands.push_str(operands);
+    }
+
+    /// Append `comment` to the comment of the instruction, separating them with a semicolon.
+    pub fn append_comment(&mut self, comment: &str) {
+        if !self.comment.is_empty() {
+            self.comment.push_str("; ");
+        }
+        self.comment.push_str(comment);
+    }
+
+    /// Attach the relocation `reloc` to the instruction, separating it from previous relocations with a semicolon.
+    pub fn add_reloc(&mut self, reloc: &str) {
+        match &mut self.reloc {
+            Some(existing) => {
+                existing.push_str("; ");
+                existing.push_str(reloc);
+            }
+            None => self.reloc = Some(reloc.to_string()),
+        }
+    }
+
+    pub fn get_reloc(&self) -> Option<&str> {
+        self.reloc.as_deref()
+    }
+
+    pub fn with_address(mut self, address: u64) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn get_address(&self) -> Option<u64> {
+        self.address
+    }
+
+    pub fn with_raw_bytes(mut self, raw_bytes: Vec<u8>) -> Self {
+        self.raw_bytes = Some(raw_bytes);
+        self
+    }
+
+    pub fn extend_raw_bytes(&mut self, raw_bytes: &[u8]) {
+        self.raw_bytes
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(raw_bytes);
+    }
+
+    pub fn get_raw_bytes(&self) -> Option<&[u8]> {
+        self.raw_bytes.as_deref()
+    }
+
+    pub fn to_string_with(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_with(&mut out, options)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Write the instruction in the layout of GNU objdump, followed by its relocation if any.
+    ///
+    /// Addresses are right aligned to `address_width` characters. Raw bytes are printed 7 per line, the ones past the
+    /// first line on lines of their own.
+    pub(crate) fn write_objdump<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        address_width: usize,
+    ) -> fmt::Result {
+        const BYTES_PER_LINE: usize = 7;
+        let write_address = |out: &mut W, offset: usize| match self.address {
+            Some(address) => write!(out, "{:>address_width$x}:", address + offset as u64),
+            None => Ok(()),
+        };
+        let format_bytes = |bytes: &[u8]| {
+            bytes
+                .iter()
+                .map(|byte| format!("{byte:02x} "))
+                .collect::<String>()
+        };
+        let mut byte_lines = self
+            .raw_bytes
+            .as_deref()
+            .unwrap_or_default()
+            .chunks(BYTES_PER_LINE);
+        write_address(out, 0)?;
+        out.write_char('\t')?;
+        if let Some(bytes) = byte_lines.next() {
+            write!(
+                out,
+                "{:<width$}\t",
+                format_bytes(bytes),
+                width = 3 * BYTES_PER_LINE
+            )?;
+        }
+        match self.kind {
+            InstructionKind::Skip => out.write_str("...")?,
+            InstructionKind::Directive => write!(out, "{}\t{}", self.opcode, self.operands)?,
+            InstructionKind::Opcode if self.operands.is_empty() => out.write_str(&self.opcode)?,
+            InstructionKind::Opcode => write!(out, "{:<6} {}", self.opcode, self.operands)?,
+        }
+        if !self.comment.is_empty() {
+            write!(out, "        # {}", self.comment)?;
+        }
+        out.write_char('\n')?;
+        for (line, bytes) in byte_lines.enumerate() {
+            write_address(out, (line + 1) * BYTES_PER_LINE)?;
+            writeln!(out, "\t{}", format_bytes(bytes))?;
+        }
+        if let Some(reloc) = &self.reloc {
+            let (reloc_type, reloc_target) = reloc.split_once(' ').unwrap_or((reloc, ""));
+            writeln!(out, "\t\t\t{reloc_type}\t{reloc_target}")?;
+        }
+        Ok(())
+    }
+
+    /// Write the instruction line, terminated by a newline, according to `options`.
+    pub(crate) fn write_with<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        options: &FormatOptions,
+    ) -> fmt::Result {
+        self.write_aligned(out, options, 6)
+    }
+
+    /// Write the instruction, padding the opcode to `opcode_width` characters when followed by operands.
+    pub(crate) fn write_aligned<W: fmt::Write>(
+        &self,
+        out: &mut W,
+        options: &FormatOptions,
+        opcode_width: usize,
+    ) -> fmt::Result {
+        if self.kind == InstructionKind::Skip {
+            return out.write_str("...\n");
+        }
+        // The arguments are the content of a directive, so they are always printed
+        let print_operands = options.operands || self.kind == InstructionKind::Directive;
+        if print_operands && !self.operands.is_empty() {
+            let operands = match options.max_operand_width {
+                Some(max_width) => elide(&self.operands, max_width),
+                None => Cow::Borrowed(self.operands.as_str()),
+            };
+            write!(out, "{:<opcode_width$} {}", self.opcode, operands)?;
+        } else {
+            out.write_str(&self.opcode)?;
+        }
+        if options.comments && !self.comment.is_empty() {
+            write!(out, "        # {}", self.comment)?;
+        }
+        out.write_char('\n')
+    }
+}
+
+/// Replace every `0x` prefixed literal, and the bare addresses preceding a `<symbol>`, with `0xADDR`.
+fn normalize_hex(text: &str) -> String {
+    lazy_static! {
+        static ref RE_HEX_LITERAL: Regex =
+            Regex::new(r"(?P<before>^|[^$[:alnum:]_])0x[[:xdigit:]]+\b").unwrap();
+        static ref RE_BARE_ADDRESS: Regex =
+            Regex::new(r"(?P<before>^|[[:space:],])[[:xdigit:]]+(?P<after>[[:space:]]+<)").unwrap();
+    }
+    let text = RE_HEX_LITERAL.replace_all(text, "${before}0xADDR");
+    RE_BARE_ADDRESS
+        .replace_all(&text, "${before}0xADDR${after}")
+        .into_owned()
+}
+
+/// Shorten `text` to `max_width` characters, replacing the last one with '…' when it is too long.
+fn elide(text: &str, max_width: usize) -> Cow<'_, str> {
+    if text.chars().count() <= max_width {
+        return Cow::Borrowed(text);
+    }
+    let mut elided: String = text.chars().take(max_width.saturating_sub(1)).collect();
+    elided.push('…');
+    Cow::Owned(elided)
 }
 
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}\n", self.opcode)
+        self.write_with(f, &FormatOptions::default())
     }
 }
 
@@ -57,9 +599,13 @@ mod tests {
         assert_eq!(
             instruction,
             Instruction {
+                kind: InstructionKind::Opcode,
+                address: None,
+                raw_bytes: None,
                 opcode: "".to_string(),
                 operands: "".to_string(),
-                comment: "".to_string()
+                comment: "".to_string(),
+                reloc: None,
             }
         )
     }
@@ -71,13 +617,316 @@ mod tests {
         assert_eq!(
             instruction,
             Instruction {
+                kind: InstructionKind::Opcode,
+                address: None,
+                raw_bytes: None,
                 opcode: "my opcode".to_string(),
                 operands: "operand1, operand 2".to_string(),
-                comment: "some kind of comment".to_string()
+                comment: "some kind of comment".to_string(),
+                reloc: None,
+            }
+        )
+    }
+
+    #[test]
+    fn new_skip_instruction_ok() {
+        let instruction = Instruction::new_skip();
+        assert_eq!(
+            instruction,
+            Instruction {
+                kind: InstructionKind::Skip,
+                address: None,
+                raw_bytes: None,
+                opcode: "".to_string(),
+                operands: "".to_string(),
+                comment: "".to_string(),
+                reloc: None,
             }
         )
     }
 
+    #[test]
+    fn accessors_ok() {
+        let instruction = Instruction::new("mov", "%rax,%rbx", "some comment");
+        assert_eq!(instruction.kind(), InstructionKind::Opcode);
+        assert_eq!(instruction.opcode(), "mov");
+        assert_eq!(instruction.operands(), "%rax,%rbx");
+        assert_eq!(instruction.comment(), "some comment");
+    }
+
+    #[test]
+    fn append_comment_ok() {
+        let mut instruction = Instruction::new("movq", "128471(%rip), %xmm0", "");
+        instruction.append_comment("xmm0 = mem[0],zero");
+        instruction.append_comment("0x245e0 <optarg>");
+        assert_eq!(
+            instruction.comment(),
+            "xmm0 = mem[0],zero; 0x245e0 <optarg>"
+        );
+    }
+
+    #[test]
+    fn target_ok() {
+        assert_eq!(
+            Instruction::new("call", "<main>", "").target(),
+            Some(BranchTarget::new("main", None))
+        );
+        assert_eq!(
+            Instruction::new("jmp", "1030 <main+0x10>", "").target(),
+            Some(BranchTarget::new("main", Some(0x10)))
+        );
+        assert_eq!(
+            Instruction::new("call", "<puts@plt>", "").target(),
+            Some(BranchTarget::new("puts@plt", None))
+        );
+    }
+
+    #[test]
+    fn target_address_ok() {
+        assert_eq!(
+            Instruction::new("jmp", "0x4011a0", "").target(),
+            Some(BranchTarget::Address(0x4011a0))
+        );
+        assert_eq!(
+            Instruction::new("bnd call", "4011a0", "").target(),
+            Some(BranchTarget::Address(0x4011a0))
+        );
+        assert_eq!(
+            Instruction::new("call", "4011a0 <foo(int, char)+0x8>", "").target(),
+            Some(BranchTarget::new("foo(int, char)", Some(0x8)))
+        );
+        assert_eq!(Instruction::new("push", "0x10", "").target(), None);
+    }
+
+    #[test]
+    fn target_missing_ok() {
+        assert_eq!(Instruction::new("mov", "%rax,%rbx", "").target(), None);
+        assert_eq!(Instruction::new("call", "*%rax", "").target(), None);
+        assert_eq!(
+            Instruction::new("mov", "0x2fd9(%rip),%rax", "3fe8 <__gmon_start__@Base>").target(),
+            None
+        );
+    }
+
+    #[test]
+    fn operand_count_ok() {
+        assert_eq!(Instruction::new("mov", "%rax,%rbx", "").operand_count(), 2);
+        assert_eq!(
+            Instruction::new("lea", "(%rbp,%rax,4)", "").operand_count(),
+            1
+        );
+        assert_eq!(Instruction::new("ret", "", "").operand_count(), 0);
+        assert_eq!(
+            Instruction::new("mov", "0x8(%rbp,%rax,4),%ecx", "").operand_count(),
+            2
+        );
+        assert_eq!(
+            Instruction::new("mov", "DWORD PTR [rbp+rax*4-0x8],0x1", "").operand_count(),
+            2
+        );
+        assert_eq!(
+            Instruction::new("call", "1040 <foo(int, char)>", "").operand_count(),
+            1
+        );
+    }
+
+    #[test]
+    fn category_ok() {
+        for (opcode, category) in [
+            ("call", OpcodeCategory::Branch),
+            ("callq", OpcodeCategory::Branch),
+            ("jne", OpcodeCategory::Branch),
+            ("add", OpcodeCategory::Arithmetic),
+            ("lock addl", OpcodeCategory::Arithmetic),
+            ("shl", OpcodeCategory::Arithmetic),
+            ("subq", OpcodeCategory::Arithmetic),
+            ("imul", OpcodeCategory::Arithmetic),
+            ("idivl", OpcodeCategory::Arithmetic),
+            ("addps", OpcodeCategory::Other),
+            ("subsd", OpcodeCategory::Other),
+            ("mulss", OpcodeCategory::Other),
+            ("divsd", OpcodeCategory::Other),
+            ("mov", OpcodeCategory::Memory),
+            ("movzbl", OpcodeCategory::Memory),
+            ("rep stos", OpcodeCategory::Memory),
+            ("pushq", OpcodeCategory::Stack),
+            ("syscall", OpcodeCategory::Syscall),
+            ("nop", OpcodeCategory::Nop),
+            ("nopw", OpcodeCategory::Nop),
+            ("vpternlogd", OpcodeCategory::Other),
+        ] {
+            assert_eq!(
+                Instruction::new(opcode, "", "").category(),
+                category,
+                "{opcode}"
+            );
+        }
+        assert_eq!(
+            Instruction::new_directive(".word", "0x1234").category(),
+            OpcodeCategory::Other
+        );
+        assert_eq!(Instruction::new_skip().category(), OpcodeCategory::Other);
+    }
+
+    #[test]
+    fn canonicalize_opcode_ok() {
+        let mut retq = Instruction::new("retq", "", "");
+        retq.canonicalize_opcode();
+        assert_eq!(retq, Instruction::new("ret", "", ""));
+
+        let mut bnd_jmpq = Instruction::new("bnd jmpq", "*%r11", "");
+        bnd_jmpq.canonicalize_opcode();
+        assert_eq!(bnd_jmpq, Instruction::new("bnd jmp", "*%r11", ""));
+
+        let mut unknown = Instruction::new("vpxor", "%xmm0,%xmm0,%xmm0", "");
+        unknown.canonicalize_opcode();
+        assert_eq!(unknown, Instruction::new("vpxor", "%xmm0,%xmm0,%xmm0", ""));
+    }
+
+    #[test]
+    fn normalized_operands_ok() {
+        let first = Instruction::new("mov", "0x357d6(%rip),%rax", "");
+        let second = Instruction::new("mov", "0x2fd9(%rip),%rax", "");
+        assert_eq!(first.normalized_operands(), "0xADDR(%rip),%rax");
+        assert_eq!(first.normalized_operands(), second.normalized_operands());
+
+        let first = Instruction::new("call", "1040 <foo+0x10>", "");
+        let second = Instruction::new("call", "2a40 <foo+0x20>", "");
+        assert_eq!(first.normalized_operands(), "0xADDR <foo+0xADDR>");
+        assert_eq!(first.normalized_operands(), second.normalized_operands());
+    }
+
+    #[test]
+    fn normalized_operands_registers_and_immediates_ok() {
+        assert_eq!(
+            Instruction::new("mov", "%r8,%rax", "").normalized_operands(),
+            "%r8,%rax"
+        );
+        assert_eq!(
+            Instruction::new("mov", "$0x10,%r10", "").normalized_operands(),
+            "$0x10,%r10"
+        );
+        assert_eq!(
+            Instruction::new("mov", "qword ptr [rbp-0x8], r10", "").normalized_operands(),
+            "qword ptr [rbp-0x8], r10"
+        );
+        assert_eq!(
+            Instruction::new("mov", "-0x8(%rbp),%rax", "").normalized_operands(),
+            "-0x8(%rbp),%rax"
+        );
+        assert_eq!(
+            Instruction::new("add", "x0, x1, #0x8", "").normalized_operands(),
+            "x0, x1, #0x8"
+        );
+        assert_eq!(
+            Instruction::new("and", "rsp,0xfffffffffffffff0", "").normalized_operands(),
+            "rsp,0xfffffffffffffff0"
+        );
+    }
+
+    #[test]
+    fn normalized_operands_targets_ok() {
+        for (opcode, operands, normalized) in [
+            ("mov", "-0x2fe2(%rip),%rax", "0xADDR(%rip),%rax"),
+            (
+                "mov",
+                "rax,QWORD PTR [rip+0x2fe2]",
+                "rax,QWORD PTR [rip+0xADDR]",
+            ),
+            ("lea", "rdi,[rip-0x1a]", "rdi,[rip-0xADDR]"),
+            ("jmp", "*0x2fe2(%rip)", "*0xADDR(%rip)"),
+            ("jmp", "0x4011a0", "0xADDR"),
+            ("call", "4011a0", "0xADDR"),
+            ("jne", "*4011a0", "*0xADDR"),
+            ("bl", "400 <foo>", "0xADDR <foo>"),
+            ("adrp", "x0, 11000 <foo+0x38>", "x0, 0xADDR <foo+0xADDR>"),
+            ("mov", "0x4011a0,%eax", "0x4011a0,%eax"),
+        ] {
+            assert_eq!(
+                Instruction::new(opcode, operands, "").normalized_operands(),
+                normalized,
+                "{opcode} {operands}"
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_addresses_ok() {
+        let mut instruction =
+            Instruction::new("lea", "0xe7b(%rip),%rax", "2004 <_IO_stdin_used+0x4>");
+        instruction.normalize_addresses();
+        assert_eq!(instruction.operands(), "0xADDR(%rip),%rax");
+        assert_eq!(instruction.comment(), "0xADDR <_IO_stdin_used+0xADDR>");
+    }
+
+    #[test]
+    fn ord_ok() {
+        let mut instructions = Vec::from([
+            Instruction::new("ret", "", ""),
+            Instruction::new("mov", "%rdi,%rax", ""),
+            Instruction::new("mov", "%rax,%rbx", "comment b"),
+            Instruction::new("mov", "%rax,%rbx", "comment a"),
+            Instruction::new("call", "<foo>", ""),
+        ]);
+        instructions.sort();
+        assert_eq!(
+            instructions,
+            [
+                Instruction::new("call", "<foo>", ""),
+                Instruction::new("mov", "%rax,%rbx", "comment a"),
+                Instruction::new("mov", "%rax,%rbx", "comment b"),
+                Instruction::new("mov", "%rdi,%rax", ""),
+                Instruction::new("ret", "", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn append_operands_ok() {
+        let mut instruction = Instruction::new("ret", "", "");
+        instruction.append_operands("$0x8");
+        assert_eq!(instruction.operands(), "$0x8");
+        instruction.append_operands("%rax");
+        assert_eq!(instruction.operands(), "$0x8 %rax");
+    }
+
+    #[test]
+    fn add_reloc_ok() {
+        let mut instruction = Instruction::new("call", "<main+0x13>", "");
+        assert_eq!(instruction.get_reloc(), None);
+        instruction.add_reloc("R_X86_64_PLT32 puts-0x4");
+        assert_eq!(instruction.get_reloc(), Some("R_X86_64_PLT32 puts-0x4"));
+        instruction.add_reloc("R_X86_64_NONE *ABS*");
+        assert_eq!(
+            instruction.get_reloc(),
+            Some("R_X86_64_PLT32 puts-0x4; R_X86_64_NONE *ABS*")
+        );
+    }
+
+    #[test]
+    fn with_address_ok() {
+        let instruction = Instruction::new("nop", "", "").with_address(0x1050);
+        assert_eq!(instruction.get_address(), Some(0x1050));
+        assert_eq!(Instruction::new("nop", "", "").get_address(), None);
+    }
+
+    #[test]
+    fn with_raw_bytes_ok() {
+        let instruction = Instruction::new("ret", "", "").with_raw_bytes(vec![0xc3]);
+        assert_eq!(instruction.get_raw_bytes(), Some([0xc3].as_slice()));
+        assert_eq!(Instruction::new("ret", "", "").get_raw_bytes(), None);
+    }
+
+    #[test]
+    fn extend_raw_bytes_ok() {
+        let mut instruction = Instruction::new("nopw", "", "").with_raw_bytes(vec![0x66, 0x0f]);
+        instruction.extend_raw_bytes(&[0x1f, 0x84]);
+        assert_eq!(
+            instruction.get_raw_bytes(),
+            Some([0x66, 0x0f, 0x1f, 0x84].as_slice())
+        );
+    }
+
     #[test]
     fn to_string_only_opcode_ok() {
         let instruction = Instruction::new("my opcode", "", "");
@@ -89,4 +938,106 @@ mod tests {
         let instruction = Instruction::new("opcode", "operands", "comment");
         assert_eq!(instruction.to_string(), "opcode\n".to_string())
     }
+
+    #[test]
+    fn to_string_with_operands_ok() {
+        let instruction = Instruction::new("mov", "%rax,%rbx", "comment");
+        let options = FormatOptions {
+            operands: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            instruction.to_string_with(&options),
+            "mov    %rax,%rbx\n".to_string()
+        )
+    }
+
+    #[test]
+    fn to_string_with_operands_long_opcode_ok() {
+        let instruction = Instruction::new("bnd jmp", "<_init+0x20>", "");
+        let options = FormatOptions {
+            operands: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            instruction.to_string_with(&options),
+            "bnd jmp <_init+0x20>\n".to_string()
+        )
+    }
+
+    #[test]
+    fn to_string_with_max_operand_width_ok() {
+        let options = FormatOptions {
+            operands: true,
+            max_operand_width: Some(10),
+            ..Default::default()
+        };
+        let short = Instruction::new("mov", "%rax,%rbx", "");
+        assert_eq!(short.to_string_with(&options), "mov    %rax,%rbx\n");
+        let long = Instruction::new("lea", "0x2fd9(%rip),%rax", "");
+        assert_eq!(long.to_string_with(&options), "lea    0x2fd9(%r…\n");
+        let multibyte = Instruction::new(".ascii", "\"ààààààààààà\"", "");
+        assert_eq!(multibyte.to_string_with(&options), ".ascii \"àààààààà…\n");
+    }
+
+    #[test]
+    fn to_string_with_operands_empty_operands_ok() {
+        let instruction = Instruction::new("ret", "", "");
+        let options = FormatOptions {
+            operands: true,
+            ..Default::default()
+        };
+        assert_eq!(instruction.to_string_with(&options), "ret\n".to_string())
+    }
+
+    #[test]
+    fn to_string_with_comments_ok() {
+        let instruction = Instruction::new("mov", "0x2fd9(%rip),%rax", "<__gmon_start__@Base>");
+        let options = FormatOptions {
+            operands: true,
+            comments: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            instruction.to_string_with(&options),
+            "mov    0x2fd9(%rip),%rax        # <__gmon_start__@Base>\n".to_string()
+        )
+    }
+
+    #[test]
+    fn to_string_with_comments_empty_comment_ok() {
+        let instruction = Instruction::new("mov", "%rax,%rbx", "");
+        let options = FormatOptions {
+            operands: true,
+            comments: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            instruction.to_string_with(&options),
+            "mov    %rax,%rbx\n".to_string()
+        )
+    }
+
+    #[test]
+    fn to_string_directive_ok() {
+        let instruction = Instruction::new_directive(".word", "0x1234");
+        assert_eq!(instruction.kind(), InstructionKind::Directive);
+        assert_eq!(instruction.to_string(), ".word  0x1234\n".to_string());
+        assert_eq!(
+            Instruction::new_directive(".asciz", "\"hi\"").to_string(),
+            ".asciz \"hi\"\n".to_string()
+        );
+    }
+
+    #[test]
+    fn to_string_skip_ok() {
+        let instruction = Instruction::new_skip();
+        let options = FormatOptions {
+            operands: true,
+            comments: true,
+            ..Default::default()
+        };
+        assert_eq!(instruction.to_string(), "...\n".to_string());
+        assert_eq!(instruction.to_string_with(&options), "...\n".to_string())
+    }
 }
@@ -0,0 +1,67 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Helpers for the Markdown output.
+//!
+//! This module contains the functions used to safely embed symbol names and instructions into Markdown.
+
+/// Escape the characters that Markdown would interpret as formatting or inline HTML.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '<' | '>' | '[' | ']' | '#' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Wrap `code` in a fenced code block, using a fence longer than any sequence of backticks in `code`.
+pub(crate) fn code_block(code: &str) -> String {
+    let longest_run = code
+        .split(|c| c != '`')
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    format!("{fence}\n{code}{fence}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_ok() {
+        assert_eq!(escape("<main>"), "\\<main\\>");
+        assert_eq!(escape("<a`b*c_d>"), "\\<a\\`b\\*c\\_d\\>");
+        assert_eq!(escape(".text"), ".text");
+    }
+
+    #[test]
+    fn code_block_ok() {
+        assert_eq!(code_block("nop\n"), "```\nnop\n```\n");
+        assert_eq!(code_block("mov ```x``\n"), "````\nmov ```x``\n````\n");
+    }
+}
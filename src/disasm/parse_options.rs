@@ -0,0 +1,73 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the ParseOptions struct.
+//!
+//! This module contains the ParseOptions struct which controls how the objdump output is parsed.
+
+use super::Demangler;
+
+/// Key used to sort the symbols of each section.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// Sort the symbols alphabetically by name.
+    #[default]
+    Name,
+    /// Sort the symbols by address, placing the symbols without an address last.
+    Address,
+}
+
+/// Objdump implementation that generated the disassembly.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// GNU objdump, from binutils.
+    #[default]
+    Gnu,
+    /// LLVM llvm-objdump.
+    Llvm,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Skip unrecognized lines instead of failing, collecting them as warnings.
+    pub lenient: bool,
+    /// Parse instructions as generated by objdump with the `-M intel` option.
+    pub intel: bool,
+    /// Parse the raw bytes column printed by objdump when `--no-show-raw-insn` is omitted.
+    pub raw_bytes: bool,
+    /// Demangle the symbol names with the given scheme before sorting them.
+    pub demangle: Option<Demangler>,
+    /// Keep sections and symbols in the order they appear in, instead of sorting them alphabetically.
+    pub keep_order: bool,
+    /// Key used to sort the symbols, ignored when keeping the objdump order.
+    pub sort_by: SortKey,
+    /// Ignore the case of the names when sorting sections and symbols.
+    pub case_insensitive_sort: bool,
+    /// Objdump implementation that generated the disassembly.
+    pub backend: Backend,
+    /// Merge the symbols of repeated section headers into the first section with the same name.
+    pub merge_duplicate_sections: bool,
+    /// Keep the opcodes exactly as printed, instead of separating their prefixes with a single space.
+    pub preserve_spacing: bool,
+    /// Character introducing the comments of the instructions, `#` when not set.
+    pub comment_char: Option<char>,
+    /// Discard the comments of the instructions, such as the resolved addresses.
+    pub strip_comments: bool,
+}
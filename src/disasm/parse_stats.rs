@@ -0,0 +1,85 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the ParseStats struct.
+//!
+//! This module contains the ParseStats struct which counts the kinds of lines recognized while parsing a disassembly.
+use std::fmt;
+
+use serde::Serialize;
+
+/// Number of lines of each kind met while parsing a disassembly, not counting the empty lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ParseStats {
+    /// Section headers, such as `Disassembly of section .text:`.
+    pub sections: usize,
+    pub symbols: usize,
+    /// Instructions and directives.
+    pub instructions: usize,
+    /// Other recognized lines, such as the file format, the '...' lines and the relocations.
+    pub other: usize,
+    /// Unrecognized lines skipped in lenient mode.
+    pub skipped: usize,
+}
+
+impl ParseStats {
+    /// Add the counts of `other`, such as the statistics of another merged disassembly.
+    pub fn add(&mut self, other: &ParseStats) {
+        self.sections += other.sections;
+        self.symbols += other.symbols;
+        self.instructions += other.instructions;
+        self.other += other.other;
+        self.skipped += other.skipped;
+    }
+}
+
+impl fmt::Display for ParseStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} section headers, {} symbols, {} instructions, {} other lines, {} skipped lines",
+            self.sections, self.symbols, self.instructions, self.other, self.skipped
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_and_to_string_ok() {
+        let mut stats = ParseStats {
+            sections: 1,
+            symbols: 2,
+            instructions: 5,
+            other: 1,
+            skipped: 0,
+        };
+        stats.add(&ParseStats {
+            skipped: 3,
+            ..stats.clone()
+        });
+        assert_eq!(
+            stats.to_string(),
+            "2 section headers, 4 symbols, 10 instructions, 2 other lines, 3 skipped lines"
+        );
+    }
+}
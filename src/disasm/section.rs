@@ -21,14 +21,25 @@
 //! Access to the Section struct.
 //!
 //! This module contains the Section struct which is a named collection of symbols.
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::fmt;
 
+use regex::Regex;
+use serde::Serialize;
+
+use super::style::{Element, StyledWrite};
+use super::DisasmError;
+use super::FormatOptions;
 use super::Instruction;
+use super::SortKey;
 use super::Symbol;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Serialize)]
 pub struct Section {
     name: String,
+    /// Flags of the section, such as `ALLOC` or `CODE`, when objdump printed its section headers.
+    flags: Vec<String>,
     symbols: Vec<Symbol>,
 }
 
@@ -36,45 +47,176 @@ impl Section {
     pub fn new(name: &str) -> Self {
         Section {
             name: name.to_string(),
+            flags: Vec::new(),
             symbols: Vec::new(),
         }
     }
 
+    /// Flags of the section, empty when they are unknown.
+    pub fn flags(&self) -> &[String] {
+        &self.flags
+    }
+
+    pub fn set_flags(&mut self, flags: Vec<String>) {
+        self.flags = flags;
+    }
+
     pub fn add_symbol(&mut self, symbol: Symbol) {
         self.symbols.push(symbol);
     }
 
-    pub fn add_instruction(&mut self, instruction: Instruction) -> Result<(), String> {
+    /// Move all the symbols of `other` at the end of this section.
+    pub fn append_symbols(&mut self, other: Section) {
+        self.symbols.extend(other.symbols);
+    }
+
+    pub fn add_instruction(&mut self, instruction: Instruction) -> Result<(), DisasmError> {
         self.symbols
             .last_mut()
-            .ok_or("Attempted to add an instruction without first defining a symbol")?
+            .ok_or(DisasmError::InstructionBeforeSymbol)?
             .add_instruction(instruction);
         Ok(())
     }
 
+    pub fn last_instruction_mut(&mut self) -> Option<&mut Instruction> {
+        self.symbols.last_mut()?.last_instruction_mut()
+    }
+
+    /// Iterate over the symbols of the section.
+    pub fn symbols(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter()
+    }
+
+    /// Iterate mutably over the symbols of the section.
+    pub fn symbols_mut(&mut self) -> impl Iterator<Item = &mut Symbol> {
+        self.symbols.iter_mut()
+    }
+
+    /// Number of symbols in the section.
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Check if the section contains no symbols.
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Keep only the symbols whose name matches `regex`.
+    pub fn retain_symbols_matching(&mut self, regex: &Regex) {
+        self.symbols.retain(|sym| regex.is_match(sym.get_name()));
+    }
+
+    /// Remove all the symbols not containing any instruction.
+    pub fn drop_empty_symbols(&mut self) {
+        self.symbols.retain(|sym| !sym.is_empty());
+    }
+
     pub fn get_name(&self) -> &String {
         &self.name
     }
 
+    /// Names of the symbols appearing more than once in the section, each listed once.
+    pub fn find_duplicate_symbols(&self) -> Vec<&str> {
+        duplicate_names(self.symbols.iter().map(|symbol| symbol.get_name().as_str()))
+    }
+
     pub fn sort_symbols(&mut self) {
-        self.symbols.sort_by(|a, b| a.get_name().cmp(b.get_name()));
+        self.sort_symbols_by(SortKey::Name, false);
+    }
+
+    /// Sort the symbols by address, symbols without an address are placed last and sorted by name.
+    pub fn sort_symbols_by_address(&mut self) {
+        self.sort_symbols_by(SortKey::Address, false);
+    }
+
+    /// Sort the symbols by `sort_by`, optionally ignoring the case of their names.
+    ///
+    /// The sort is stable, symbols comparing equal keep their relative order.
+    pub fn sort_symbols_by(&mut self, sort_by: SortKey, case_insensitive: bool) {
+        self.sort_symbols_with(|a, b| compare_symbols(a, b, sort_by, case_insensitive));
     }
+
+    /// Sort the symbols with the comparator `compare`, the sort is stable.
+    pub fn sort_symbols_with<F>(&mut self, compare: F)
+    where
+        F: FnMut(&Symbol, &Symbol) -> Ordering,
+    {
+        self.symbols.sort_by(compare);
+    }
+
+    pub fn to_string_with(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_with(&mut out, options, 0)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Write the section at the nesting level `depth`, followed by its symbols one level deeper.
+    pub(crate) fn write_with<W: StyledWrite>(
+        &self,
+        out: &mut W,
+        options: &FormatOptions,
+        depth: usize,
+    ) -> fmt::Result {
+        options.write_indent(out, depth)?;
+        out.set_style(Element::Section)?;
+        out.write_str(&self.name)?;
+        out.reset_style()?;
+        out.write_str(":")?;
+        if options.section_flags && !self.flags.is_empty() {
+            write!(out, " {}", self.flags.join(", "))?;
+        }
+        out.write_str("\n")?;
+        for symbol in &self.symbols {
+            symbol.write_with(out, options, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compare two symbols by `sort_by`, then by name.
+pub(crate) fn compare_symbols(
+    a: &Symbol,
+    b: &Symbol,
+    sort_by: SortKey,
+    case_insensitive: bool,
+) -> Ordering {
+    let by_address = match (sort_by, a.get_address(), b.get_address()) {
+        (SortKey::Name, _, _) => Ordering::Equal,
+        (SortKey::Address, Some(addr_a), Some(addr_b)) => addr_a.cmp(&addr_b),
+        (SortKey::Address, Some(_), None) => Ordering::Less,
+        (SortKey::Address, None, Some(_)) => Ordering::Greater,
+        (SortKey::Address, None, None) => Ordering::Equal,
+    };
+    by_address.then_with(|| compare_names(a.get_name(), b.get_name(), case_insensitive))
+}
+
+/// Compare two names, optionally ignoring their case.
+pub(crate) fn compare_names(a: &str, b: &str, case_insensitive: bool) -> Ordering {
+    if case_insensitive {
+        a.to_lowercase().cmp(&b.to_lowercase())
+    } else {
+        a.cmp(b)
+    }
+}
+
+/// Names appearing more than once in `names`, each listed once and sorted.
+pub(crate) fn duplicate_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<&'a str> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for name in names {
+        *counts.entry(name).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(name, _)| name)
+        .collect()
 }
 
 impl fmt::Display for Section {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Stringify all symbols and concatenate them
-        let symbols_str = self
-            .symbols
-            .iter()
-            .fold("".to_string(), |acc, x| acc + &x.to_string());
-        // Add fours spaces before each line
-        let symbols_str = symbols_str.split('\n').fold("".to_string(), |acc, x| {
-            acc + if !x.is_empty() { "    " } else { "" }
-                + x
-                + if !x.is_empty() { "\n" } else { "" }
-        });
-        write!(f, "{}:\n{}", self.name, symbols_str)
+        self.write_with(f, &FormatOptions::default(), 0)
     }
 }
 
@@ -90,6 +232,7 @@ mod tests {
             section,
             Section {
                 name: "".to_string(),
+                flags: Vec::new(),
                 symbols: Vec::new(),
             }
         )
@@ -102,6 +245,7 @@ mod tests {
             section,
             Section {
                 name: "Section name".to_string(),
+                flags: Vec::new(),
                 symbols: Vec::new(),
             }
         )
@@ -116,6 +260,7 @@ mod tests {
             section,
             Section {
                 name: "sec".to_string(),
+                flags: Vec::new(),
                 symbols: Vec::from([Symbol::new("sym1"), Symbol::new("sym2")]),
             }
         )
@@ -125,10 +270,10 @@ mod tests {
     fn add_instruction_single_symbol_single_instruction_fails() {
         let mut section = Section::new("sec");
         let add_instr_res = section.add_instruction(Instruction::new("", "", ""));
-        assert_eq!(
+        assert!(matches!(
             add_instr_res,
-            Err("Attempted to add an instruction without first defining a symbol".to_string())
-        );
+            Err(DisasmError::InstructionBeforeSymbol)
+        ));
     }
 
     #[test]
@@ -145,6 +290,7 @@ mod tests {
             section,
             Section {
                 name: "sec".to_string(),
+                flags: Vec::new(),
                 symbols: Vec::from([comparison_sym]),
             }
         );
@@ -168,11 +314,49 @@ mod tests {
             section,
             Section {
                 name: "sec".to_string(),
+                flags: Vec::new(),
                 symbols: Vec::from([comparison_sym1, comparison_sym2]),
             }
         );
     }
 
+    #[test]
+    fn symbols_len_and_is_empty_ok() {
+        let mut section = Section::new("sec");
+        assert_eq!(section.symbols().count(), 0);
+        assert_eq!(section.len(), 0);
+        assert!(section.is_empty());
+
+        section.add_symbol(Symbol::new("sym1"));
+        section.add_symbol(Symbol::new("sym2"));
+        assert_eq!(
+            section
+                .symbols()
+                .map(|sym| sym.get_name())
+                .collect::<Vec<_>>(),
+            ["sym1", "sym2"]
+        );
+        assert_eq!(section.len(), 2);
+        assert!(!section.is_empty());
+    }
+
+    #[test]
+    fn retain_symbols_matching_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("<main>"));
+        section.add_symbol(Symbol::new("<irq_handler>"));
+        section.add_symbol(Symbol::new("<handler_table>"));
+        section.retain_symbols_matching(&Regex::new(r"^<.*handler>$").unwrap());
+        assert_eq!(
+            section,
+            Section {
+                name: "sec".to_string(),
+                flags: Vec::new(),
+                symbols: Vec::from([Symbol::new("<irq_handler>")]),
+            }
+        );
+    }
+
     #[test]
     fn get_name_with_no_name_ok() {
         let section = Section::new("");
@@ -185,6 +369,18 @@ mod tests {
         assert_eq!(section.get_name(), "symbol name")
     }
 
+    #[test]
+    fn find_duplicate_symbols_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("<main>"));
+        section.add_symbol(Symbol::new("<foo>"));
+        assert!(section.find_duplicate_symbols().is_empty());
+
+        section.add_symbol(Symbol::new("<main>"));
+        section.add_symbol(Symbol::new("<main>"));
+        assert_eq!(section.find_duplicate_symbols(), ["<main>"]);
+    }
+
     #[test]
     fn sort_symbols_ok() {
         let mut section = Section::new("sec");
@@ -198,6 +394,7 @@ mod tests {
             section,
             Section {
                 name: "sec".to_string(),
+                flags: Vec::new(),
                 symbols: Vec::from([
                     Symbol::new("asym"),
                     Symbol::new("sym1"),
@@ -209,6 +406,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sort_symbols_case_insensitive_ok() {
+        let mut section = Section::new("sec");
+        for name in ["beta", "Alpha", "alpha", "Beta", "ALPHA"] {
+            section.add_symbol(Symbol::new(name));
+        }
+        let names = |section: &Section| {
+            section
+                .symbols()
+                .map(|sym| sym.get_name().clone())
+                .collect::<Vec<_>>()
+        };
+
+        section.sort_symbols_by(SortKey::Name, true);
+        assert_eq!(names(&section), ["Alpha", "alpha", "ALPHA", "beta", "Beta"]);
+
+        section.sort_symbols();
+        assert_eq!(names(&section), ["ALPHA", "Alpha", "Beta", "alpha", "beta"]);
+    }
+
     #[test]
     fn to_string_unnamed_empty_section_ok() {
         let section = Section::new("");
@@ -225,19 +442,16 @@ mod tests {
     fn to_string_named_and_non_empty_section_ok() {
         let mut section = Section::new("sec");
         section.add_symbol(Symbol::new("sym1"));
-        assert_eq!(
-            section.add_instruction(Instruction::new("nop", "", "")),
-            Ok(())
-        );
-        assert_eq!(
-            section.add_instruction(Instruction::new("mov", "-0x1198(%rbp),%rax", "")),
-            Ok(())
-        );
+        assert!(section
+            .add_instruction(Instruction::new("nop", "", ""))
+            .is_ok());
+        assert!(section
+            .add_instruction(Instruction::new("mov", "-0x1198(%rbp),%rax", ""))
+            .is_ok());
         section.add_symbol(Symbol::new("sym2"));
-        assert_eq!(
-            section.add_instruction(Instruction::new("lea", "0x357d6(%rip),%rcx", "")),
-            Ok(())
-        );
+        assert!(section
+            .add_instruction(Instruction::new("lea", "0x357d6(%rip),%rcx", ""))
+            .is_ok());
 
         assert_eq!(
             section.to_string(),
@@ -252,4 +466,140 @@ mod tests {
             .to_string()
         )
     }
+
+    #[test]
+    fn to_string_with_operands_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("sym1"));
+        assert!(section
+            .add_instruction(Instruction::new("mov", "-0x1198(%rbp),%rax", ""))
+            .is_ok());
+        assert!(section
+            .add_instruction(Instruction::new("nop", "", ""))
+            .is_ok());
+
+        assert_eq!(
+            section.to_string_with(&FormatOptions {
+                operands: true,
+                ..Default::default()
+            }),
+            indoc! {"
+                sec:
+                    sym1:
+                        mov    -0x1198(%rbp),%rax
+                        nop
+            "}
+            .to_string()
+        )
+    }
+
+    #[test]
+    fn append_symbols_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("sym2"));
+        let mut other = Section::new("sec");
+        other.add_symbol(Symbol::new("sym1"));
+        section.append_symbols(other);
+        assert_eq!(
+            section.symbols().collect::<Vec<_>>(),
+            [&Symbol::new("sym2"), &Symbol::new("sym1")]
+        );
+    }
+
+    #[test]
+    fn sort_symbols_by_address_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("zsym"));
+        section.add_symbol(Symbol::new("sym2").with_address(0x1040));
+        section.add_symbol(Symbol::new("asym"));
+        section.add_symbol(Symbol::new("sym1").with_address(0x1080));
+        section.add_symbol(Symbol::new("sym3").with_address(0x1000));
+        section.sort_symbols_by_address();
+        assert_eq!(
+            section,
+            Section {
+                name: "sec".to_string(),
+                flags: Vec::new(),
+                symbols: Vec::from([
+                    Symbol::new("sym3").with_address(0x1000),
+                    Symbol::new("sym2").with_address(0x1040),
+                    Symbol::new("sym1").with_address(0x1080),
+                    Symbol::new("asym"),
+                    Symbol::new("zsym"),
+                ])
+            }
+        )
+    }
+
+    #[test]
+    fn sort_symbols_by_address_without_addresses_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("sym2"));
+        section.add_symbol(Symbol::new("sym1"));
+        section.add_symbol(Symbol::new("sym2"));
+        section.sort_symbols_by_address();
+        assert_eq!(
+            section,
+            Section {
+                name: "sec".to_string(),
+                flags: Vec::new(),
+                symbols: Vec::from([
+                    Symbol::new("sym1"),
+                    Symbol::new("sym2"),
+                    Symbol::new("sym2"),
+                ])
+            }
+        )
+    }
+
+    #[test]
+    fn to_string_with_two_spaces_indent_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("sym1"));
+        let _ = section.add_instruction(Instruction::new("nop", "", ""));
+
+        assert_eq!(
+            section.to_string_with(&FormatOptions {
+                indent: "  ".to_string(),
+                ..Default::default()
+            }),
+            "sec:\n  sym1:\n    nop\n".to_string()
+        )
+    }
+
+    #[test]
+    fn to_string_with_section_flags_ok() {
+        let mut section = Section::new("sec");
+        section.set_flags(vec!["ALLOC".to_string(), "CODE".to_string()]);
+        section.add_symbol(Symbol::new("sym1"));
+
+        let options = FormatOptions {
+            section_flags: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            section.to_string_with(&options),
+            "sec: ALLOC, CODE\n    sym1:\n".to_string()
+        );
+        assert_eq!(
+            section.to_string_with(&FormatOptions::default()),
+            "sec:\n    sym1:\n".to_string()
+        );
+    }
+
+    #[test]
+    fn to_string_with_tabs_indent_ok() {
+        let mut section = Section::new("sec");
+        section.add_symbol(Symbol::new("sym1"));
+        let _ = section.add_instruction(Instruction::new("nop", "", ""));
+        section.add_symbol(Symbol::new("sym2"));
+
+        assert_eq!(
+            section.to_string_with(&FormatOptions {
+                indent: "\t".to_string(),
+                ..Default::default()
+            }),
+            "sec:\n\tsym1:\n\t\tnop\n\tsym2:\n".to_string()
+        )
+    }
 }
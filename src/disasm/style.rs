@@ -0,0 +1,131 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the styled writers.
+//!
+//! This module contains the StyledWrite trait used by the text output to mark the start and end of each element,
+//! and the ColorWriter adapter which turns these marks into terminal colors.
+use std::fmt;
+use std::io;
+
+use termcolor::{Color, ColorSpec, WriteColor};
+
+/// Element of the text output that can be styled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Element {
+    Section,
+    Symbol,
+}
+
+/// Text sink which can apply a style to the elements written into it, plain sinks ignore the styles.
+pub(crate) trait StyledWrite: fmt::Write {
+    fn set_style(&mut self, _element: Element) -> fmt::Result {
+        Ok(())
+    }
+
+    fn reset_style(&mut self) -> fmt::Result {
+        Ok(())
+    }
+}
+
+impl StyledWrite for String {}
+
+impl StyledWrite for fmt::Formatter<'_> {}
+
+/// Adapter writing text into a `WriteColor` sink, coloring sections in bold blue and symbols in green.
+pub(crate) struct ColorWriter<W: WriteColor> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: WriteColor> ColorWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        ColorWriter { inner, error: None }
+    }
+
+    /// Convert the result of a formatting operation, recovering the underlying io error if any.
+    pub(crate) fn into_result(self, result: fmt::Result) -> io::Result<()> {
+        match (result, self.error) {
+            (Ok(()), _) => Ok(()),
+            (Err(_), Some(error)) => Err(error),
+            (Err(_), None) => Err(io::Error::from(io::ErrorKind::Other)),
+        }
+    }
+
+    fn store_error(&mut self, result: io::Result<()>) -> fmt::Result {
+        result.map_err(|error| {
+            self.error = Some(error);
+            fmt::Error
+        })
+    }
+}
+
+impl<W: WriteColor> fmt::Write for ColorWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let result = self.inner.write_all(s.as_bytes());
+        self.store_error(result)
+    }
+}
+
+impl<W: WriteColor> StyledWrite for ColorWriter<W> {
+    fn set_style(&mut self, element: Element) -> fmt::Result {
+        let mut spec = ColorSpec::new();
+        match element {
+            Element::Section => spec.set_fg(Some(Color::Blue)).set_bold(true),
+            Element::Symbol => spec.set_fg(Some(Color::Green)),
+        };
+        let result = self.inner.set_color(&spec);
+        self.store_error(result)
+    }
+
+    fn reset_style(&mut self) -> fmt::Result {
+        let result = self.inner.reset();
+        self.store_error(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fmt::Write;
+    use termcolor::{Ansi, NoColor};
+
+    #[test]
+    fn color_writer_ansi_ok() {
+        let mut writer = ColorWriter::new(Ansi::new(Vec::new()));
+        let result = writer
+            .set_style(Element::Symbol)
+            .and_then(|_| writer.write_str("<main>"))
+            .and_then(|_| writer.reset_style());
+        assert!(result.is_ok());
+        assert_eq!(writer.inner.into_inner(), b"\x1b[0m\x1b[32m<main>\x1b[0m");
+    }
+
+    #[test]
+    fn color_writer_no_color_ok() {
+        let mut writer = ColorWriter::new(NoColor::new(Vec::new()));
+        let result = writer
+            .set_style(Element::Section)
+            .and_then(|_| writer.write_str(".text"))
+            .and_then(|_| writer.reset_style());
+        assert!(result.is_ok());
+        assert_eq!(writer.inner.into_inner(), b".text");
+    }
+}
@@ -0,0 +1,79 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Access to the Summary struct.
+//!
+//! This module contains the Summary struct which holds the overall size of a disassembly, and the SectionStat struct
+//! which holds the size of a single section.
+use std::fmt;
+
+use serde::Serialize;
+
+/// Number of sections, symbols and instructions of a disassembly.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Summary {
+    pub file_name: String,
+    pub file_format: String,
+    pub sections: usize,
+    pub symbols: usize,
+    /// Number of instructions, not counting the '...' lines and the directives.
+    pub instructions: usize,
+}
+
+/// Number of symbols and instructions, and size, of a section.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SectionStat {
+    pub name: String,
+    pub symbols: usize,
+    /// Number of instructions, not counting the '...' lines and the directives.
+    pub instructions: usize,
+    /// Size in bytes of the symbols, when known for all of them, see [`Symbol::byte_size`](super::Symbol::byte_size).
+    pub bytes: Option<u64>,
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}, {} sections, {} symbols, {} instructions",
+            self.file_name, self.file_format, self.sections, self.symbols, self.instructions
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_string_ok() {
+        let summary = Summary {
+            file_name: "hello_world.o".to_string(),
+            file_format: "elf64-x86-64".to_string(),
+            sections: 2,
+            symbols: 5,
+            instructions: 17,
+        };
+        assert_eq!(
+            summary.to_string(),
+            "hello_world.o: elf64-x86-64, 2 sections, 5 symbols, 17 instructions"
+        );
+    }
+}
@@ -21,13 +21,37 @@
 //! Access to the Symbol struct.
 //!
 //! This module contains the Symbol struct which is a named collection of instructions.
+use std::borrow::Borrow;
 use std::fmt;
+use std::hash::Hasher;
 
-use super::Instruction;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+use super::hash::Fnv1a;
+use super::style::{Element, StyledWrite};
+use super::FormatOptions;
+use super::{Instruction, InstructionKind};
+
+/// Demangled C++ operators containing angle brackets, longest first.
+const OPERATORS_WITH_BRACKETS: &[&str] = &[
+    "operator<<=",
+    "operator>>=",
+    "operator<=>",
+    "operator->*",
+    "operator<<",
+    "operator>>",
+    "operator<=",
+    "operator>=",
+    "operator->",
+    "operator<",
+    "operator>",
+];
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Symbol {
     name: String,
+    address: Option<u64>,
     instructions: Vec<Instruction>,
 }
 
@@ -35,28 +59,231 @@ impl Symbol {
     pub fn new(name: &str) -> Self {
         Symbol {
             name: name.to_string(),
+            address: None,
             instructions: Vec::new(),
         }
     }
 
+    /// Check that `name` is enclosed in a single pair of angle brackets, such as `<main>` or `<foo<int>>`.
+    ///
+    /// Nested pairs must be balanced and can't close the outer one early, as in `<a><b>`. The brackets of demangled C++
+    /// operators, such as `operator<`, are ignored.
+    pub(crate) fn is_well_formed_name(name: &str) -> bool {
+        let inner = match name
+            .strip_prefix('<')
+            .and_then(|name| name.strip_suffix('>'))
+        {
+            Some(inner) => inner,
+            None => return false,
+        };
+        // Demangled C++ operators contain unbalanced angle brackets
+        let inner = OPERATORS_WITH_BRACKETS
+            .iter()
+            .fold(inner.to_string(), |inner, operator| {
+                inner.replace(operator, "operator")
+            });
+        let mut depth = 0usize;
+        for c in inner.chars() {
+            match c {
+                '<' => depth += 1,
+                '>' if depth == 0 => return false,
+                '>' => depth -= 1,
+                _ => (),
+            }
+        }
+        depth == 0
+    }
+
+    pub fn with_address(mut self, address: u64) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    pub fn get_address(&self) -> Option<u64> {
+        self.address
+    }
+
     pub fn add_instruction(&mut self, instruction: Instruction) {
         self.instructions.push(instruction);
     }
 
+    pub fn last_instruction_mut(&mut self) -> Option<&mut Instruction> {
+        self.instructions.last_mut()
+    }
+
+    /// Iterate over the instructions of the symbol.
+    pub fn instructions(&self) -> impl Iterator<Item = &Instruction> {
+        self.instructions.iter()
+    }
+
+    /// Iterate mutably over the instructions of the symbol.
+    pub fn instructions_mut(&mut self) -> impl Iterator<Item = &mut Instruction> {
+        self.instructions.iter_mut()
+    }
+
+    /// Sort the instructions by opcode, operands and comment, losing their original order.
+    pub fn sort_instructions(&mut self) {
+        self.instructions.sort();
+    }
+
+    /// Keep only the instructions whose opcode is contained in `opcodes`.
+    pub fn retain_instructions_matching(&mut self, opcodes: &[String]) {
+        self.instructions
+            .retain(|instruction| opcodes.iter().any(|opcode| opcode == instruction.opcode()));
+    }
+
+    /// Number of instructions in the symbol.
+    pub fn len(&self) -> usize {
+        self.instructions.len()
+    }
+
+    /// Check if the symbol contains no instructions.
+    pub fn is_empty(&self) -> bool {
+        self.instructions.is_empty()
+    }
+
+    /// Number of instructions in the symbol, not counting the '...' lines and the directives.
+    pub fn instruction_count(&self) -> usize {
+        self.instructions
+            .iter()
+            .filter(|instruction| instruction.kind() == InstructionKind::Opcode)
+            .count()
+    }
+
+    /// Size in bytes, from the lowest address of the instructions to the end of the highest one.
+    ///
+    /// The instructions may be in any order, such as after sorting them. Needs the addresses and the raw bytes of the
+    /// instructions, it's `None` otherwise.
+    pub fn byte_size(&self) -> Option<u64> {
+        let mut start: Option<u64> = None;
+        let mut end: Option<u64> = None;
+        for instruction in &self.instructions {
+            let Some(address) = instruction.get_address() else {
+                continue;
+            };
+            let len = instruction.get_raw_bytes()?.len() as u64;
+            start = Some(start.map_or(address, |start| start.min(address)));
+            end = Some(end.map_or(address + len, |end| end.max(address + len)));
+        }
+        end?.checked_sub(start?)
+    }
+
+    /// Stable hash of the sequence of opcodes, also covering the operands with normalized addresses if
+    /// `include_operands` is set.
+    ///
+    /// Symbols with the same body have the same hash, regardless of their name and address.
+    pub fn content_hash(&self, include_operands: bool) -> u64 {
+        let mut hasher = Fnv1a::new();
+        for instruction in &self.instructions {
+            hasher.write(instruction.opcode().as_bytes());
+            if include_operands {
+                hasher.write_u8(0);
+                hasher.write(instruction.normalized_operands().as_bytes());
+            }
+            hasher.write_u8(0xff);
+        }
+        hasher.finish()
+    }
+
     pub fn get_name(&self) -> &String {
         &self.name
     }
+
+    pub fn to_string_with(&self, options: &FormatOptions) -> String {
+        let mut out = String::new();
+        self.write_with(&mut out, options, 0)
+            .expect("writing to a String can't fail");
+        out
+    }
+
+    /// Write the symbol at the nesting level `depth`, followed by its instructions one level deeper.
+    pub(crate) fn write_with<W: StyledWrite>(
+        &self,
+        out: &mut W,
+        options: &FormatOptions,
+        depth: usize,
+    ) -> fmt::Result {
+        write_symbol(out, &self.name, &self.instructions, options, depth)
+    }
+}
+
+/// Write the symbol `name` at the nesting level `depth`, followed by `instructions` one level deeper.
+///
+/// The instructions are borrowed so that symbols merged from several sections can be written without copying them.
+pub(crate) fn write_symbol<W: StyledWrite, I: Borrow<Instruction>>(
+    out: &mut W,
+    name: &str,
+    instructions: &[I],
+    options: &FormatOptions,
+    depth: usize,
+) -> fmt::Result {
+    options.write_indent(out, depth)?;
+    out.set_style(Element::Symbol)?;
+    out.write_str(name)?;
+    out.reset_style()?;
+    out.write_str(":\n")?;
+    let opcode_width = if options.align {
+        instructions
+            .iter()
+            .map(|instruction| instruction.borrow().opcode().chars().count())
+            .max()
+            .unwrap_or_default()
+    } else {
+        6
+    };
+    let mut remaining = instructions;
+    while let Some(instruction) = remaining.first().map(Borrow::borrow) {
+        // Only count the repeats when collapsing them, to keep long runs linear otherwise
+        let repeats = match options.collapse_repeats {
+            Some(_) => remaining
+                .iter()
+                .take_while(|other| (*other).borrow() == instruction)
+                .count(),
+            None => 1,
+        };
+        options.write_indent(out, depth + 1)?;
+        match options.collapse_repeats {
+            Some(threshold) if repeats >= threshold => {
+                let mut line = String::new();
+                instruction.write_aligned(&mut line, options, opcode_width)?;
+                writeln!(out, "{} (x {repeats})", line.trim_end_matches('\n'))?;
+                remaining = &remaining[repeats..];
+            }
+            _ => {
+                instruction.write_aligned(out, options, opcode_width)?;
+                remaining = &remaining[1..];
+            }
+        }
+        if let Some(reloc) = instruction.get_reloc().filter(|_| options.relocations) {
+            options.write_indent(out, depth + 2)?;
+            writeln!(out, "{reloc}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Symbol of a disassembly together with the name of its section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolRef<'a> {
+    pub section: &'a str,
+    pub symbol: &'a Symbol,
 }
 
 impl fmt::Display for Symbol {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let joined = self
-            .instructions
-            .iter()
-            .map(|sec| format!("    {}", sec))
-            .collect::<Vec<_>>()
-            .join("");
-        write!(f, "{}:\n{}", self.name, joined)
+        self.write_with(f, &FormatOptions::default(), 0)
+    }
+}
+
+// The byte size is derived from the instructions, so it is computed while serializing
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Symbol", 4)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("address", &self.address)?;
+        state.serialize_field("byte_size", &self.byte_size())?;
+        state.serialize_field("instructions", &self.instructions)?;
+        state.end()
     }
 }
 
@@ -72,6 +299,7 @@ mod tests {
             symbol,
             Symbol {
                 name: "".to_string(),
+                address: None,
                 instructions: Vec::new(),
             }
         )
@@ -84,6 +312,36 @@ mod tests {
             symbol,
             Symbol {
                 name: "symbol name".to_string(),
+                address: None,
+                instructions: Vec::new(),
+            }
+        )
+    }
+
+    #[test]
+    fn is_well_formed_name_ok() {
+        assert!(Symbol::is_well_formed_name("<foo>"));
+        assert!(Symbol::is_well_formed_name("<foo<int>>"));
+        assert!(!Symbol::is_well_formed_name("<foo<bar>"));
+        assert!(!Symbol::is_well_formed_name("<a><b>"));
+        assert!(!Symbol::is_well_formed_name("foo"));
+        assert!(Symbol::is_well_formed_name(
+            "<A::operator<(A const&) const>"
+        ));
+        assert!(Symbol::is_well_formed_name(
+            "<std::ostream& std::operator<< <std::char_traits<char> >(std::ostream&, char const*)>"
+        ));
+    }
+
+    #[test]
+    fn with_address_ok() {
+        let symbol = Symbol::new("symbol name").with_address(0x1040);
+        assert_eq!(symbol.get_address(), Some(0x1040));
+        assert_eq!(
+            symbol,
+            Symbol {
+                name: "symbol name".to_string(),
+                address: Some(0x1040),
                 instructions: Vec::new(),
             }
         )
@@ -98,6 +356,7 @@ mod tests {
             symbol,
             Symbol {
                 name: "sym".to_string(),
+                address: None,
                 instructions: Vec::from([
                     Instruction::new("nop", "", ""),
                     Instruction::new("bnd jmp", "<_init+0x20>", "")
@@ -106,6 +365,147 @@ mod tests {
         )
     }
 
+    #[test]
+    fn instructions_len_and_is_empty_ok() {
+        let mut symbol = Symbol::new("sym");
+        assert_eq!(symbol.instructions().count(), 0);
+        assert_eq!(symbol.len(), 0);
+        assert!(symbol.is_empty());
+
+        symbol.add_instruction(Instruction::new("nop", "", ""));
+        symbol.add_instruction(Instruction::new("ret", "", ""));
+        assert_eq!(
+            symbol.instructions().collect::<Vec<_>>(),
+            [
+                &Instruction::new("nop", "", ""),
+                &Instruction::new("ret", "", "")
+            ]
+        );
+        assert_eq!(symbol.len(), 2);
+        assert!(!symbol.is_empty());
+    }
+
+    #[test]
+    fn byte_size_out_of_order_ok() {
+        let mut symbol = Symbol::new("sym").with_address(0x1130);
+        symbol.add_instruction(
+            Instruction::new("ret", "", "")
+                .with_address(0x1138)
+                .with_raw_bytes(vec![0xc3]),
+        );
+        symbol.add_instruction(
+            Instruction::new("push", "%rbp", "")
+                .with_address(0x1130)
+                .with_raw_bytes(vec![0x55]),
+        );
+        symbol.add_instruction(
+            Instruction::new("mov", "%rsp,%rbp", "")
+                .with_address(0x1131)
+                .with_raw_bytes(vec![0x48, 0x89, 0xe5]),
+        );
+        assert_eq!(symbol.byte_size(), Some(9));
+
+        symbol.sort_instructions();
+        assert_eq!(symbol.byte_size(), Some(9));
+    }
+
+    #[test]
+    fn byte_size_ok() {
+        let mut symbol = Symbol::new("sym").with_address(0x1130);
+        assert_eq!(symbol.byte_size(), None);
+
+        symbol.add_instruction(
+            Instruction::new("push", "%rbp", "")
+                .with_address(0x1130)
+                .with_raw_bytes(vec![0x55]),
+        );
+        symbol.add_instruction(
+            Instruction::new("mov", "%rsp,%rbp", "")
+                .with_address(0x1131)
+                .with_raw_bytes(vec![0x48, 0x89, 0xe5]),
+        );
+        symbol.add_instruction(Instruction::new_skip());
+        symbol.add_instruction(
+            Instruction::new("ret", "", "")
+                .with_address(0x1138)
+                .with_raw_bytes(vec![0xc3]),
+        );
+        assert_eq!(symbol.byte_size(), Some(9));
+
+        let mut missing_raw_bytes = Symbol::new("sym");
+        missing_raw_bytes.add_instruction(Instruction::new("nop", "", "").with_address(0x1130));
+        missing_raw_bytes.add_instruction(
+            Instruction::new("ret", "", "")
+                .with_address(0x1131)
+                .with_raw_bytes(vec![0xc3]),
+        );
+        assert_eq!(missing_raw_bytes.byte_size(), None);
+
+        let mut no_addresses = Symbol::new("sym");
+        no_addresses.add_instruction(Instruction::new("ret", "", "").with_raw_bytes(vec![0xc3]));
+        assert_eq!(no_addresses.byte_size(), None);
+    }
+
+    #[test]
+    fn instruction_count_ok() {
+        let mut symbol = Symbol::new("sym");
+        assert_eq!(symbol.instruction_count(), 0);
+
+        symbol.add_instruction(Instruction::new("nop", "", ""));
+        symbol.add_instruction(Instruction::new_skip());
+        symbol.add_instruction(Instruction::new("ret", "", ""));
+        assert_eq!(symbol.instruction_count(), 2);
+        assert_eq!(symbol.len(), 3);
+    }
+
+    #[test]
+    fn retain_instructions_matching_ok() {
+        let mut symbol = Symbol::new("sym");
+        symbol.add_instruction(Instruction::new("push", "%rbp", ""));
+        symbol.add_instruction(Instruction::new("mov", "%rsp,%rbp", ""));
+        symbol.add_instruction(Instruction::new("movl", "$0x0,%eax", ""));
+        symbol.add_instruction(Instruction::new("mov", "%rdi,%rax", ""));
+        symbol.add_instruction(Instruction::new_skip());
+        symbol.add_instruction(Instruction::new("ret", "", ""));
+        symbol.retain_instructions_matching(&["mov".to_string()]);
+        assert_eq!(
+            symbol.instructions().collect::<Vec<_>>(),
+            [
+                &Instruction::new("mov", "%rsp,%rbp", ""),
+                &Instruction::new("mov", "%rdi,%rax", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn content_hash_ok() {
+        let mut symbol = Symbol::new("sym").with_address(0x1040);
+        symbol.add_instruction(Instruction::new("push", "%rbp", ""));
+        symbol.add_instruction(Instruction::new("call", "1050 <foo>", ""));
+        symbol.add_instruction(Instruction::new("ret", "", ""));
+        let mut same = Symbol::new("other").with_address(0x2040);
+        same.add_instruction(Instruction::new("push", "%rbp", ""));
+        same.add_instruction(Instruction::new("call", "2050 <foo>", ""));
+        same.add_instruction(Instruction::new("ret", "", ""));
+        let mut changed = Symbol::new("sym");
+        changed.add_instruction(Instruction::new("push", "%rbp", ""));
+        changed.add_instruction(Instruction::new("jmp", "1050 <foo>", ""));
+        changed.add_instruction(Instruction::new("ret", "", ""));
+        let mut other_operands = Symbol::new("sym");
+        other_operands.add_instruction(Instruction::new("push", "%rbx", ""));
+        other_operands.add_instruction(Instruction::new("call", "1050 <foo>", ""));
+        other_operands.add_instruction(Instruction::new("ret", "", ""));
+
+        assert_eq!(symbol.content_hash(false), same.content_hash(false));
+        assert_eq!(symbol.content_hash(true), same.content_hash(true));
+        assert_ne!(symbol.content_hash(false), changed.content_hash(false));
+        assert_eq!(
+            symbol.content_hash(false),
+            other_operands.content_hash(false)
+        );
+        assert_ne!(symbol.content_hash(true), other_operands.content_hash(true));
+    }
+
     #[test]
     fn get_name_with_no_name_ok() {
         let symbol = Symbol::new("");
@@ -145,4 +545,102 @@ mod tests {
             .to_string()
         )
     }
+
+    #[test]
+    fn to_string_with_relocations_ok() {
+        let mut symbol = Symbol::new("sym");
+        let mut call = Instruction::new("call", "<sym+0x13>", "");
+        call.add_reloc("R_X86_64_PLT32 puts-0x4");
+        symbol.add_instruction(call);
+        symbol.add_instruction(Instruction::new("ret", "", ""));
+        assert_eq!(symbol.to_string(), "sym:\n    call\n    ret\n".to_string());
+        assert_eq!(
+            symbol.to_string_with(&FormatOptions {
+                relocations: true,
+                ..Default::default()
+            }),
+            indoc! {"
+                sym:
+                    call
+                        R_X86_64_PLT32 puts-0x4
+                    ret
+            "}
+            .to_string()
+        )
+    }
+
+    #[test]
+    fn to_string_with_collapse_repeats_ok() {
+        let mut symbol = Symbol::new("sym");
+        symbol.add_instruction(Instruction::new("push", "%rbp", ""));
+        for _ in 0..5 {
+            symbol.add_instruction(Instruction::new("nop", "", ""));
+        }
+        symbol.add_instruction(Instruction::new("ret", "", ""));
+        symbol.add_instruction(Instruction::new("ret", "", ""));
+        let options = FormatOptions {
+            collapse_repeats: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            symbol.to_string_with(&options),
+            indoc! {"
+                sym:
+                    push
+                    nop (x 5)
+                    ret
+                    ret
+            "}
+        )
+    }
+
+    #[test]
+    fn to_string_with_align_ok() {
+        let mut symbol = Symbol::new("sym");
+        symbol.add_instruction(Instruction::new("nop", "", ""));
+        symbol.add_instruction(Instruction::new("mov", "%rax,%rbx", ""));
+        symbol.add_instruction(Instruction::new("vpbroadcastq", "%xmm0,%ymm1", ""));
+        symbol.add_instruction(Instruction::new("bnd jmp", "<_init+0x20>", ""));
+        for _ in 0..3 {
+            symbol.add_instruction(Instruction::new("push", "$0x0", ""));
+        }
+        let options = FormatOptions {
+            operands: true,
+            align: true,
+            collapse_repeats: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            symbol.to_string_with(&options),
+            indoc! {"
+                sym:
+                    nop
+                    mov          %rax,%rbx
+                    vpbroadcastq %xmm0,%ymm1
+                    bnd jmp      <_init+0x20>
+                    push         $0x0 (x 3)
+            "}
+        )
+    }
+
+    #[test]
+    fn to_string_with_operands_ok() {
+        let mut symbol = Symbol::new("sym");
+        symbol.add_instruction(Instruction::new("nop", "", ""));
+        symbol.add_instruction(Instruction::new("bnd jmp", "<_init+0x20>", ""));
+        symbol.add_instruction(Instruction::new("mov", "%rax,%rbx", ""));
+        assert_eq!(
+            symbol.to_string_with(&FormatOptions {
+                operands: true,
+                ..Default::default()
+            }),
+            indoc! {"
+                sym:
+                    nop
+                    bnd jmp <_init+0x20>
+                    mov    %rax,%rbx
+            "}
+            .to_string()
+        )
+    }
 }
@@ -0,0 +1,44 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+//! Parse and pretty print the output of objdump.
+//!
+//! The output of `objdump -d --no-addresses --no-show-raw-insn` is parsed into a [`Disasm`], either with
+//! [`Disasm::try_from`] using the default parsing options or with [`Disasm::from_lines`] using custom
+//! [`ParseOptions`].
+//!
+//! ```
+//! use disasm_util::Disasm;
+//!
+//! let text = "file.o:     file format elf64-x86-64\n\
+//!             Disassembly of section .text:\n\
+//!             <main>:\n\
+//!             \tret\n";
+//! let disasm = Disasm::try_from(text.to_string()).unwrap();
+//! assert_eq!(disasm.to_string(), ".text:\n    <main>:\n        ret\n");
+//! ```
+
+mod disasm;
+
+pub use disasm::{
+    Backend, BranchTarget, Demangler, DiffReport, Disasm, DisasmError, FileFormat, Fnv1a,
+    FormatOptions, Instruction, InstructionKind, OpcodeCategory, ParseOptions, ParseStats, Section,
+    SectionDiff, SectionStat, SortKey, Summary, Symbol, SymbolRef, CODE_SECTIONS_PATTERN,
+};
@@ -18,26 +18,133 @@
  * SPDX-License-Identifier: Apache-2.0
  */
 
-use clap::Parser;
-use std::fs::write;
-use std::io::{self, ErrorKind, Write};
+use clap::{ArgGroup, Parser, ValueEnum};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, ErrorKind, IsTerminal, Write};
+use std::iter;
 use std::path::Path;
-use std::process::Command;
+use std::process::{Command, ExitCode};
 use std::str;
+use std::time::UNIX_EPOCH;
+use termcolor::Ansi;
 
-mod disasm;
+use disasm_util::{
+    Backend, Demangler, Disasm, Fnv1a, FormatOptions, OpcodeCategory, ParseOptions, SortKey,
+    Summary, CODE_SECTIONS_PATTERN,
+};
 
-use disasm::Disasm;
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// Indented plain text
+    Text,
+    /// Pretty printed JSON
+    Json,
+    /// One comma separated row per instruction
+    Csv,
+    /// Markdown headings and code blocks
+    Markdown,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorMode {
+    /// Color the output when printing to a terminal
+    Auto,
+    /// Always color the output
+    Always,
+    /// Never color the output
+    Never,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DemangleScheme {
+    /// Rust symbols
+    Rust,
+    /// C++ symbols, according to the Itanium ABI
+    Cpp,
+    /// Both rust and C++ symbols
+    Auto,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PathStyle {
+    /// Forward slashes, as in `folder/file`
+    Unix,
+    /// Backslashes, as in `folder\\file`
+    Windows,
+}
+
+impl PathStyle {
+    fn separator(self) -> char {
+        match self {
+            PathStyle::Unix => '/',
+            PathStyle::Windows => '\\',
+        }
+    }
+}
+
+impl From<DemangleScheme> for Demangler {
+    fn from(scheme: DemangleScheme) -> Self {
+        match scheme {
+            DemangleScheme::Rust => Demangler::Rust,
+            DemangleScheme::Cpp => Demangler::Cpp,
+            DemangleScheme::Auto => Demangler::Auto,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ObjdumpBackend {
+    /// GNU objdump
+    Gnu,
+    /// LLVM llvm-objdump
+    Llvm,
+}
+
+impl From<ObjdumpBackend> for Backend {
+    fn from(backend: ObjdumpBackend) -> Self {
+        match backend {
+            ObjdumpBackend::Gnu => Backend::Gnu,
+            ObjdumpBackend::Llvm => Backend::Llvm,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortBy {
+    /// Symbol name
+    Name,
+    /// Symbol address, symbols without an address are placed last
+    Address,
+}
+
+impl From<SortBy> for SortKey {
+    fn from(sort_by: SortBy) -> Self {
+        match sort_by {
+            SortBy::Name => SortKey::Name,
+            SortBy::Address => SortKey::Address,
+        }
+    }
+}
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("output_mode").multiple(false).conflicts_with("format")))]
 struct Cli {
     #[arg(
-        value_parser = path_parse,
+        value_parser = obj_file_parse,
         value_name = "OBJ-FILE",
-        help="Disassemble <OBJ-FILE>"
+        required_unless_present = "path_input_dump",
+        help="Disassemble and merge each <OBJ-FILE>, use '-' to read objdump output from stdin"
     )]
-    path_obj_file: String,
+    path_obj_files: Vec<String>,
     #[arg(
         short='e',
         long = "executable",
@@ -46,6 +153,53 @@ struct Cli {
         help="Use the objdump executable <FILE>"
     )]
     path_objdump: Option<String>,
+    #[arg(
+        long = "backend",
+        value_enum,
+        default_value_t = ObjdumpBackend::Gnu,
+        help = "Disassemble and parse using the <BACKEND> objdump implementation"
+    )]
+    backend: ObjdumpBackend,
+    #[arg(
+        long = "cache-dir",
+        value_name = "DIR",
+        conflicts_with = "path_input_dump",
+        help = "Cache the objdump output in <DIR>, reusing it until the object file changes"
+    )]
+    cache_dir: Option<String>,
+    #[arg(
+        long = "function",
+        value_name = "NAME",
+        conflicts_with = "path_input_dump",
+        help = "Only disassemble the function <NAME>, letting objdump skip the rest of the file"
+    )]
+    function: Option<String>,
+    #[arg(
+        long = "print-command",
+        conflicts_with = "path_input_dump",
+        help = "Print the objdump command that would be run for each object file, without running it"
+    )]
+    print_command: bool,
+    #[arg(
+        long = "show-tool-version",
+        conflicts_with = "path_input_dump",
+        help = "Print the version of the objdump executable to stderr before disassembling"
+    )]
+    show_tool_version: bool,
+    #[arg(
+        long = "input-dump",
+        value_name = "FILE",
+        value_parser = path_parse,
+        conflicts_with = "path_objdump",
+        help = "Parse the objdump output stored in <FILE> instead of running objdump"
+    )]
+    path_input_dump: Option<String>,
+    #[arg(
+        long = "gzip",
+        requires = "path_input_dump",
+        help = "Decompress the dump file as gzip, the default for files ending in .gz"
+    )]
+    gzip: bool,
     #[arg(
         short = 'o',
         long = "out",
@@ -53,52 +207,1104 @@ struct Cli {
         help = "Place the output into <FILE>"
     )]
     path_out_file: Option<String>,
+    #[arg(
+        long = "gzip-out",
+        requires = "path_out_file",
+        help = "Compress the output file with gzip"
+    )]
+    gzip_out: bool,
+    #[arg(
+        long = "tee",
+        requires = "path_out_file",
+        help = "Also print the output to stdout when placing it into a file"
+    )]
+    tee: bool,
+    #[arg(
+        long = "no-trailing-newline",
+        help = "Strip the newline at the end of the output"
+    )]
+    no_trailing_newline: bool,
+    #[arg(long = "operands", help = "Print the operands of each instruction")]
+    operands: bool,
+    #[arg(
+        long = "max-operand-width",
+        value_name = "N",
+        requires = "operands",
+        help = "Elide the operands longer than <N> characters"
+    )]
+    max_operand_width: Option<usize>,
+    #[arg(
+        long = "align",
+        requires = "operands",
+        help = "Pad the opcodes to the longest one of each symbol, aligning the operands into a column"
+    )]
+    align: bool,
+    #[arg(
+        long = "collapse-repeats",
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "3",
+        help = "Print runs of at least <N> identical instructions once, with their count [default: 3]"
+    )]
+    collapse_repeats: Option<usize>,
+    #[arg(long = "comments", help = "Print the comment of each instruction")]
+    comments: bool,
+    #[arg(
+        long = "indent",
+        value_name = "N",
+        default_value_t = 4,
+        help = "Indent each nesting level with <N> spaces"
+    )]
+    indent: usize,
+    #[arg(
+        long = "tabs",
+        conflicts_with = "indent",
+        help = "Indent each nesting level with a tab"
+    )]
+    tabs: bool,
+    #[arg(
+        long = "format",
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Print the output in the <FORMAT> format"
+    )]
+    format: OutputFormat,
+    #[arg(
+        long = "color",
+        value_name = "WHEN",
+        value_enum,
+        default_value_t = ColorMode::Auto,
+        help = "Color the text output <WHEN>"
+    )]
+    color: ColorMode,
+    #[arg(
+        long = "verify-filename",
+        conflicts_with = "path_input_dump",
+        help = "Fail if the file name printed by objdump doesn't match the object file"
+    )]
+    verify_filename: bool,
+    #[arg(
+        long = "normalize-path-sep",
+        value_name = "STYLE",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "unix",
+        help = "Use the <STYLE> path separator in the file name printed by objdump"
+    )]
+    normalize_path_sep: Option<PathStyle>,
+    #[arg(
+        long = "strict-symbols",
+        help = "Fail if a section contains more than one symbol with the same name"
+    )]
+    strict_symbols: bool,
+    #[arg(
+        long = "merge-duplicate-sections",
+        help = "Merge the symbols of sections whose header is repeated, instead of keeping them separate"
+    )]
+    merge_duplicate_sections: bool,
+    #[arg(
+        long = "preserve-spacing",
+        help = "Keep the spacing between the opcodes and their prefixes as printed by objdump"
+    )]
+    preserve_spacing: bool,
+    #[arg(
+        long = "comment-char",
+        value_name = "CHAR",
+        help = "Split the comments of the instructions at <CHAR> instead of '#'"
+    )]
+    comment_char: Option<char>,
+    #[arg(
+        long = "strip-comments",
+        conflicts_with = "comments",
+        help = "Discard the comments of the instructions while parsing, also from the JSON and CSV outputs"
+    )]
+    strip_comments: bool,
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        help = "Don't print warnings to stderr, errors are still reported"
+    )]
+    quiet: bool,
+    #[arg(long = "lenient", help = "Skip unrecognized lines instead of failing")]
+    lenient: bool,
+    #[arg(
+        long = "parse-stats",
+        help = "Print to stderr how many lines of each kind were parsed"
+    )]
+    parse_stats: bool,
+    #[arg(long = "intel", help = "Disassemble and parse using the Intel syntax")]
+    intel: bool,
+    #[arg(
+        long = "addresses",
+        help = "Disassemble keeping the address of each symbol and instruction"
+    )]
+    addresses: bool,
+    #[arg(
+        long = "raw-bytes",
+        help = "Disassemble keeping the raw bytes of each instruction"
+    )]
+    raw_bytes: bool,
+    #[arg(
+        long = "relocations",
+        help = "Disassemble keeping the relocations and print them below each instruction"
+    )]
+    relocations: bool,
+    #[arg(
+        long = "with-flags",
+        help = "Disassemble printing the section headers and print the flags of each section"
+    )]
+    with_flags: bool,
+    #[arg(
+        long = "no-sort",
+        help = "Keep sections and symbols in the objdump order instead of sorting them"
+    )]
+    no_sort: bool,
+    #[arg(
+        long = "sort-by",
+        value_enum,
+        default_value_t = SortBy::Name,
+        conflicts_with = "no_sort",
+        help = "Sort the symbols of each section by <SORT_BY>"
+    )]
+    sort_by: SortBy,
+    #[arg(
+        long = "sort-case-insensitive",
+        conflicts_with = "no_sort",
+        help = "Ignore the case of section and symbol names when sorting"
+    )]
+    sort_case_insensitive: bool,
+    #[arg(
+        long = "normalize-addresses",
+        help = "Replace the hex addresses in operands and comments with a placeholder"
+    )]
+    normalize_addresses: bool,
+    #[arg(
+        long = "canonicalize-mnemonics",
+        help = "Replace mnemonics spelled differently by some objdump versions, such as retq, with their canonical form"
+    )]
+    canonicalize_mnemonics: bool,
+    #[arg(
+        long = "sort-instructions",
+        help = "Sort the instructions of each symbol by opcode, operands and comment, for set comparisons"
+    )]
+    sort_instructions: bool,
+    #[arg(
+        long = "section",
+        value_name = "NAME",
+        help = "Only print the section <NAME>, can be repeated"
+    )]
+    sections: Vec<String>,
+    #[arg(
+        long = "symbol-regex",
+        value_name = "PATTERN",
+        value_parser = regex_parse,
+        help = "Only print the symbols matching <PATTERN>"
+    )]
+    symbol_regex: Option<Regex>,
+    #[arg(
+        long = "opcode",
+        value_name = "NAME",
+        help = "Only print the instructions with opcode <NAME>, can be repeated"
+    )]
+    opcodes: Vec<String>,
+    #[arg(
+        long = "only-code",
+        help = "Only print the sections containing code, such as .text, .plt, .init and .fini"
+    )]
+    only_code: bool,
+    #[arg(
+        long = "code-section-regex",
+        value_name = "PATTERN",
+        value_parser = regex_parse,
+        default_value = CODE_SECTIONS_PATTERN,
+        requires = "only_code",
+        help = "Consider as code the sections matching <PATTERN> when using --only-code"
+    )]
+    code_section_regex: Regex,
+    #[arg(
+        long = "drop-empty-sections",
+        alias = "drop-empty",
+        help = "Do not print the sections left without symbols after filtering"
+    )]
+    drop_empty_sections: bool,
+    #[arg(
+        long = "allow-empty",
+        help = "Exit successfully when no section, or no instruction with --count-instructions, is left to print"
+    )]
+    allow_empty: bool,
+    #[arg(
+        long = "demangle",
+        value_name = "SCHEME",
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "auto",
+        help = "Demangle symbol names using <SCHEME>"
+    )]
+    demangle: Option<DemangleScheme>,
+    #[arg(
+        long = "objdump-demangle",
+        conflicts_with = "demangle",
+        help = "Let objdump demangle the symbol names, running it with the -C option"
+    )]
+    objdump_demangle: bool,
+    #[arg(
+        long = "wide",
+        help = "Run objdump with the -w option, so that long lines are not wrapped"
+    )]
+    wide: bool,
+    #[arg(
+        long = "histogram",
+        group = "output_mode",
+        help = "Print the number of occurrences of each opcode instead of the disassembly"
+    )]
+    histogram: bool,
+    #[arg(
+        long = "count-instructions",
+        group = "output_mode",
+        help = "Print the number of instructions of each symbol instead of the disassembly"
+    )]
+    count_instructions: bool,
+    #[arg(
+        long = "diff",
+        value_name = "OTHER-OBJ-FILE",
+        value_parser = path_parse,
+        group = "output_mode",
+        help = "Print the symbols added, removed or changed in <OTHER-OBJ-FILE>"
+    )]
+    path_diff_obj_file: Option<String>,
+    #[arg(
+        long = "callgraph",
+        group = "output_mode",
+        help = "Print the call graph between symbols in the Graphviz DOT format"
+    )]
+    callgraph: bool,
+    #[arg(
+        long = "find-clones",
+        group = "output_mode",
+        help = "Print the groups of symbols with identical opcodes instead of the disassembly"
+    )]
+    find_clones: bool,
+    #[arg(
+        long = "list-empty-symbols",
+        group = "output_mode",
+        help = "Print the symbols containing no instructions instead of the disassembly"
+    )]
+    list_empty_symbols: bool,
+    #[arg(
+        long = "section-stats",
+        group = "output_mode",
+        help = "Print the number of symbols and instructions of each section instead of the disassembly"
+    )]
+    section_stats: bool,
+    #[arg(
+        long = "list-sections",
+        group = "output_mode",
+        help = "Print the sorted names of the sections instead of the disassembly"
+    )]
+    list_sections: bool,
+    #[arg(
+        long = "list-symbols",
+        group = "output_mode",
+        help = "Print the section and name of every symbol, separated by a tab, instead of the disassembly"
+    )]
+    list_symbols: bool,
+    #[arg(
+        long = "by-symbol",
+        group = "output_mode",
+        help = "Print the instructions grouped by symbol name, merging the symbols with the same name across sections"
+    )]
+    by_symbol: bool,
+    #[arg(
+        long = "hash",
+        group = "output_mode",
+        help = "Print a hash of the opcodes of each symbol, also covering the operands with --operands"
+    )]
+    hash: bool,
+    #[arg(
+        long = "flat",
+        conflicts_with_all = ["format", "output_mode"],
+        help = "Print one line per instruction, prefixed by its section and symbol, instead of the indented hierarchy"
+    )]
+    flat: bool,
+    #[arg(
+        long = "track",
+        value_name = "STATE-FILE",
+        group = "output_mode",
+        help = "Print the symbols changed since the hashes stored in <STATE-FILE>, then store the current hashes into it"
+    )]
+    track: Option<String>,
+    #[arg(
+        long = "category-stats",
+        group = "output_mode",
+        help = "Print the number of instructions of each x86 opcode category instead of the disassembly"
+    )]
+    category_stats: bool,
+    #[arg(
+        long = "faithful",
+        group = "output_mode",
+        help = "Print the disassembly in the objdump layout and order instead of the indented hierarchy"
+    )]
+    faithful: bool,
+    #[arg(
+        long = "summary",
+        conflicts_with = "output_mode",
+        help = "Prepend the number of sections, symbols and instructions to the output"
+    )]
+    summary: bool,
+}
+
+/// JSON output of the disassembly with its summary as an additional top-level field.
+#[derive(Serialize)]
+struct JsonWithSummary<'a> {
+    summary: Summary,
+    #[serde(flatten)]
+    disasm: &'a Disasm,
+}
+
+/// Writer holding back the last newline written, so that the output doesn't end with it.
+struct NoTrailingNewline<W: Write> {
+    inner: W,
+    pending: bool,
+}
+
+impl<W: Write> NoTrailingNewline<W> {
+    fn new(inner: W) -> Self {
+        NoTrailingNewline {
+            inner,
+            pending: false,
+        }
+    }
+}
+
+impl<W: Write> Write for NoTrailingNewline<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A held newline was not the last one, write it before the rest
+        if self.pending {
+            self.inner.write_all(b"\n")?;
+        }
+        self.pending = buf.ends_with(b"\n");
+        let len = buf.len() - usize::from(self.pending);
+        self.inner.write_all(&buf[..len])?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// File written with `--out`, compressed with `--gzip-out`.
+enum OutFile {
+    Plain(File),
+    Gzip(GzEncoder<File>),
+}
+
+impl OutFile {
+    /// Flush the file, writing the gzip trailer if compressed.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            OutFile::Plain(mut file) => file.flush(),
+            OutFile::Gzip(encoder) => encoder.finish().map(drop),
+        }
+    }
+}
+
+impl Write for OutFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            OutFile::Plain(file) => file.write(buf),
+            OutFile::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            OutFile::Plain(file) => file.flush(),
+            OutFile::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writer duplicating the output into all the sinks, such as the output file and stdout with `--tee`.
+struct Tee<'a>(Vec<&'a mut dyn Write>);
+
+impl Write for Tee<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for sink in &mut self.0 {
+            sink.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for sink in &mut self.0 {
+            sink.flush()?;
+        }
+        Ok(())
+    }
 }
 
 fn path_parse(path: &str) -> Result<String, String> {
     if Path::new(path).is_file() {
         Ok(path.to_string())
     } else {
-        Err(format!("File does not exist!"))
+        Err("File does not exist!".to_string())
     }
 }
 
-fn main() -> Result<(), String> {
-    let cli = Cli::parse();
+fn regex_parse(pattern: &str) -> Result<Regex, String> {
+    Regex::new(pattern).map_err(|msg| msg.to_string())
+}
+
+fn obj_file_parse(path: &str) -> Result<String, String> {
+    if path == "-" {
+        Ok(path.to_string())
+    } else {
+        path_parse(path)
+    }
+}
 
-    let objdump = cli.path_objdump.unwrap_or("objdump".to_string());
+fn objdump_executable(cli: &Cli) -> &str {
+    match (&cli.path_objdump, cli.backend) {
+        (Some(path_objdump), _) => path_objdump,
+        (None, ObjdumpBackend::Gnu) => "objdump",
+        (None, ObjdumpBackend::Llvm) => "llvm-objdump",
+    }
+}
 
-    let objdump_res = Command::new(&objdump)
-        .args([
-            "-d",
-            "--no-addresses",
-            "--no-show-raw-insn",
-            &cli.path_obj_file,
-        ])
+fn objdump_args(cli: &Cli, path_obj_file: &str) -> Vec<String> {
+    let mut args = vec![match (&cli.function, cli.backend) {
+        (Some(function), ObjdumpBackend::Gnu) => format!("--disassemble={function}"),
+        (Some(function), ObjdumpBackend::Llvm) => format!("--disassemble-symbols={function}"),
+        (None, _) => "-d".to_string(),
+    }];
+    // Symbol addresses are needed to sort by address, and with the raw bytes to compute the section sizes, even if
+    // they are not printed
+    if !cli.addresses && cli.sort_by != SortBy::Address && !cli.section_stats {
+        args.push(match cli.backend {
+            ObjdumpBackend::Gnu => "--no-addresses".to_string(),
+            ObjdumpBackend::Llvm => "--no-leading-addr".to_string(),
+        });
+    }
+    if !cli.raw_bytes && !cli.section_stats {
+        args.push("--no-show-raw-insn".to_string());
+    }
+    if cli.relocations {
+        args.push("-r".to_string());
+    }
+    if cli.with_flags {
+        args.push("-h".to_string());
+    }
+    if cli.objdump_demangle {
+        args.push("-C".to_string());
+    }
+    if cli.wide {
+        args.push("-w".to_string());
+    }
+    if cli.intel {
+        args.extend(["-M".to_string(), "intel".to_string()]);
+    }
+    args.push(path_obj_file.to_string());
+    args
+}
+
+/// Check that the file name printed by objdump refers to `path_obj_file`.
+fn verify_file_name(disasm: &Disasm, path_obj_file: &str) -> Result<(), String> {
+    let file_name = disasm.get_file_name();
+    if Some(OsStr::new(disasm.file_base_name())) == Path::new(path_obj_file).file_name() {
+        Ok(())
+    } else {
+        Err(format!(
+            "The disassembly is for '{file_name}' instead of '{path_obj_file}'"
+        ))
+    }
+}
+
+fn apply_filters(cli: &Cli, disasm: &mut Disasm) {
+    if let Some(style) = cli.normalize_path_sep {
+        disasm.normalize_path_separator(style.separator());
+    }
+    if cli.normalize_addresses {
+        disasm.normalize_addresses();
+    }
+    if cli.canonicalize_mnemonics {
+        disasm.canonicalize_mnemonics();
+    }
+    if cli.sort_instructions {
+        disasm.sort_instructions();
+    }
+    if !cli.sections.is_empty() {
+        disasm.retain_sections(&cli.sections);
+    }
+    if cli.only_code {
+        disasm.retain_sections_matching(&cli.code_section_regex);
+    }
+    if let Some(symbol_regex) = &cli.symbol_regex {
+        disasm.retain_symbols_matching(symbol_regex);
+    }
+    if !cli.opcodes.is_empty() {
+        disasm.retain_instructions_matching(&cli.opcodes);
+        if cli.drop_empty_sections {
+            disasm.drop_empty_symbols();
+        }
+    }
+    // Runs last, to also drop the sections emptied by the other filters
+    if cli.drop_empty_sections {
+        disasm.drop_empty_sections();
+    }
+}
+
+fn use_color(cli: &Cli) -> bool {
+    // Files always get the plain text output
+    cli.path_out_file.is_none()
+        && match cli.color {
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+            ColorMode::Auto => io::stdout().is_terminal(),
+        }
+}
+
+fn histogram_to_string(histogram: BTreeMap<String, usize>) -> String {
+    let mut entries = histogram.into_iter().collect::<Vec<_>>();
+    // Most frequent opcodes first, ties are kept in alphabetical order
+    entries.sort_by(|(_, count_a), (_, count_b)| count_b.cmp(count_a));
+    entries
+        .iter()
+        .map(|(opcode, count)| format!("{count:>8} {opcode}\n"))
+        .collect()
+}
+
+fn category_stats_to_string(stats: BTreeMap<OpcodeCategory, usize>) -> String {
+    stats
+        .iter()
+        .map(|(category, count)| format!("{count:>8} {category}\n"))
+        .collect()
+}
+
+fn instruction_counts_to_string(disasm: &Disasm) -> String {
+    let mut counts = disasm.instruction_counts().collect::<Vec<_>>();
+    // Largest symbols first, ties are kept in the disassembly order
+    counts.sort_by(|(_, _, count_a), (_, _, count_b)| count_b.cmp(count_a));
+    counts
+        .iter()
+        .map(|(section, symbol, count)| format!("{count:>8} {section} {symbol}\n"))
+        .collect()
+}
+
+fn section_stats_to_string(disasm: &Disasm) -> String {
+    let stats = disasm.section_stats();
+    let total_symbols: usize = stats.iter().map(|stat| stat.symbols).sum();
+    let total_instructions: usize = stats.iter().map(|stat| stat.instructions).sum();
+    let total_bytes: Option<u64> = stats.iter().map(|stat| stat.bytes).sum();
+    // The size is only known with the addresses and the raw bytes of the instructions
+    let bytes = |bytes: Option<u64>| bytes.map_or("-".to_string(), |bytes| bytes.to_string());
+    let mut table = format!(
+        "{:>12} {:>8} {:>8} section\n",
+        "instructions", "symbols", "bytes"
+    );
+    for stat in &stats {
+        table += &format!(
+            "{:>12} {:>8} {:>8} {}\n",
+            stat.instructions,
+            stat.symbols,
+            bytes(stat.bytes),
+            stat.name
+        );
+    }
+    table
+        + &format!(
+            "{total_instructions:>12} {total_symbols:>8} {:>8} total\n",
+            bytes(total_bytes)
+        )
+}
+
+fn clone_clusters_to_string(disasm: &Disasm) -> String {
+    disasm
+        .clone_clusters()
+        .iter()
+        .map(|cluster| {
+            cluster
+                .iter()
+                .map(|sym_ref| format!("{} {}\n", sym_ref.section, sym_ref.symbol.get_name()))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn callgraph_to_dot(disasm: &Disasm) -> String {
+    let quote = |name: &str| format!("\"{}\"", name.replace('"', "\\\""));
+    let symbols = disasm
+        .sections()
+        .flat_map(|section| section.symbols())
+        .map(|symbol| symbol.get_name())
+        .collect::<Vec<_>>();
+    let edges = disasm.call_edges();
+
+    let mut dot = String::from("digraph callgraph {\n");
+    for symbol in &symbols {
+        dot += &format!("    {};\n", quote(symbol));
+    }
+    // References to symbols outside the disassembly are drawn as dashed nodes
+    let mut external = Vec::new();
+    for (_, target) in &edges {
+        if !symbols.contains(&target) && !external.contains(&target) {
+            external.push(target);
+            dot += &format!("    {} [style=dashed];\n", quote(target));
+        }
+    }
+    for (source, target) in &edges {
+        dot += &format!("    {} -> {};\n", quote(source), quote(target));
+    }
+    dot + "}\n"
+}
+
+/// Report the symbols changed since the hashes stored in the `--track` state file, then update it.
+fn track_changes(cli: &Cli, disasm: &Disasm) -> Result<String, String> {
+    let path_state = cli.track.as_deref().unwrap_or_default();
+    // The first run starts from an empty state, reporting all symbols as added
+    let previous: BTreeMap<String, u64> = match fs::read_to_string(path_state) {
+        Ok(state) => serde_json::from_str(&state)
+            .map_err(|msg| format!("Invalid state file '{path_state}': {msg}"))?,
+        Err(err) if err.kind() == ErrorKind::NotFound => BTreeMap::new(),
+        Err(err) => return Err(err.to_string()),
+    };
+    let report = disasm.diff_hashes(&previous, cli.operands);
+    let current = serde_json::to_string_pretty(&disasm.symbol_hashes(cli.operands))
+        .map_err(|msg| msg.to_string())?;
+    fs::write(path_state, current + "\n").map_err(|msg| msg.to_string())?;
+    Ok(report.to_string())
+}
+
+/// Quote `arg` for a POSIX shell when it contains special characters.
+fn shell_quote(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_=./:,+@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// First line printed by `objdump --version`.
+fn objdump_version(objdump: &str) -> Result<String, String> {
+    let output = Command::new(objdump)
+        .arg("--version")
+        .output()
+        .map_err(|msg| format!("Failed to run '{objdump} --version': {msg}"))?;
+    let stdout = str::from_utf8(&output.stdout).map_err(|msg| msg.to_string())?;
+    Ok(stdout.lines().next().unwrap_or_default().to_string())
+}
+
+/// Run objdump on `path_obj_file`, reusing its output from the cache directory when possible.
+///
+/// Cached outputs are keyed by the objdump executable, its arguments and the modification time of the object file,
+/// so they are invalidated when the object file changes.
+fn disassemble(cli: &Cli, path_obj_file: &str) -> Result<String, String> {
+    let objdump = objdump_executable(cli);
+    let args = objdump_args(cli, path_obj_file);
+    let cache_dir = match &cli.cache_dir {
+        Some(cache_dir) => Path::new(cache_dir),
+        None => return run_objdump_with_fallback(objdump, &args, cli.quiet),
+    };
+    let modified = fs::metadata(path_obj_file)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|msg| msg.to_string())?;
+    let modified = modified
+        .duration_since(UNIX_EPOCH)
+        .map_err(|msg| msg.to_string())?;
+    // The key is hashed with a fixed algorithm, so the cache stays valid across builds of the tool
+    let mut hasher = Fnv1a::new();
+    for part in iter::once(objdump).chain(args.iter().map(String::as_str)) {
+        hasher.write(part.as_bytes());
+        hasher.write_u8(0);
+    }
+    hasher.write(&modified.as_secs().to_le_bytes());
+    hasher.write(&modified.subsec_nanos().to_le_bytes());
+    let path_cached = cache_dir.join(format!("{:016x}.dis", hasher.finish()));
+    if let Ok(stdout) = fs::read_to_string(&path_cached) {
+        return Ok(stdout);
+    }
+    let stdout = run_objdump_with_fallback(objdump, &args, cli.quiet)?;
+    fs::create_dir_all(cache_dir)
+        .and_then(|_| fs::write(&path_cached, &stdout))
+        .map_err(|msg| {
+            format!(
+                "Failed to write the cache file {}: {msg}",
+                path_cached.display()
+            )
+        })?;
+    Ok(stdout)
+}
+
+/// Run objdump, retrying without `--no-addresses` when it is not supported.
+///
+/// The option was added in binutils 2.35, the addresses printed by older versions are handled by the parser.
+fn run_objdump_with_fallback(
+    objdump: &str,
+    args: &[String],
+    quiet: bool,
+) -> Result<String, String> {
+    match run_objdump(objdump, args, quiet) {
+        Err(msg) if msg.contains("unrecognized option '--no-addresses'") => {
+            if !quiet {
+                eprintln!(
+                    "'{objdump}' doesn't support --no-addresses, retrying without it. \
+                     Use binutils 2.35 or newer to remove the addresses from the operands"
+                );
+            }
+            let args: Vec<String> = args
+                .iter()
+                .filter(|arg| *arg != "--no-addresses")
+                .cloned()
+                .collect();
+            run_objdump(objdump, &args, quiet)
+        }
+        result => result,
+    }
+}
+
+fn run_objdump(objdump: &str, args: &[String], quiet: bool) -> Result<String, String> {
+    let objdump_res = Command::new(objdump)
+        .args(args)
         .output()
         .map_err(|e| match e.kind() {
             ErrorKind::NotFound => {
-                "'objdump' was not found! Check your PATH or explicitly provide an executable"
-                    .to_string()
+                format!("'{objdump}' was not found! Check your PATH or explicitly provide an executable")
             }
             _ => e.to_string(),
         })?;
 
     let stderr = str::from_utf8(&objdump_res.stderr).map_err(|msg| msg.to_string())?;
-    if !stderr.is_empty() {
-        return Err(stderr.to_string());
+    if !objdump_res.status.success() {
+        return Err(format!(
+            "'{objdump}' failed with {}\n{stderr}",
+            objdump_res.status
+        ));
+    }
+
+    let stdout = str::from_utf8(&objdump_res.stdout).map_err(|msg| msg.to_string())?;
+    if stdout.trim().is_empty() {
+        return Err(format!(
+            "'{objdump}' exited successfully but printed no disassembly\n{stderr}"
+        ));
+    }
+
+    // Warnings don't prevent objdump from producing a valid disassembly
+    if !quiet {
+        eprint!("{stderr}");
+    }
+    Ok(stdout.to_string())
+}
+
+fn main() -> Result<ExitCode, String> {
+    let cli = Cli::parse();
+
+    let format_options = FormatOptions {
+        operands: cli.operands,
+        comments: cli.comments,
+        indent: if cli.tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(cli.indent)
+        },
+        relocations: cli.relocations,
+        max_operand_width: cli.max_operand_width,
+        collapse_repeats: cli.collapse_repeats,
+        align: cli.align,
+        section_flags: cli.with_flags,
+    };
+    let parse_options = ParseOptions {
+        lenient: cli.lenient,
+        intel: cli.intel,
+        raw_bytes: cli.raw_bytes || cli.section_stats,
+        demangle: cli.demangle.map(Demangler::from),
+        keep_order: cli.no_sort || cli.faithful,
+        sort_by: SortKey::from(cli.sort_by),
+        case_insensitive_sort: cli.sort_case_insensitive,
+        backend: Backend::from(cli.backend),
+        merge_duplicate_sections: cli.merge_duplicate_sections,
+        preserve_spacing: cli.preserve_spacing,
+        comment_char: cli.comment_char,
+        strip_comments: cli.strip_comments,
+    };
+    if cli.print_command {
+        let objdump = objdump_executable(&cli);
+        // No command is run for the objdump output read from stdin
+        let path_obj_files = cli.path_obj_files.iter().filter(|path| *path != "-");
+        for path_obj_file in path_obj_files.chain(&cli.path_diff_obj_file) {
+            let command = std::iter::once(objdump.to_string())
+                .chain(objdump_args(&cli, path_obj_file))
+                .map(|arg| shell_quote(&arg))
+                .collect::<Vec<_>>();
+            println!("{}", command.join(" "));
+        }
+        return Ok(ExitCode::SUCCESS);
+    }
+    if cli.show_tool_version {
+        eprintln!("{}", objdump_version(objdump_executable(&cli))?);
+    }
+    let mut disasm = match (&cli.path_input_dump, cli.path_obj_files.as_slice()) {
+        (Some(path_input_dump), _) => {
+            let file = File::open(path_input_dump).map_err(|msg| msg.to_string())?;
+            if cli.gzip || path_input_dump.ends_with(".gz") {
+                Disasm::from_reader(BufReader::new(GzDecoder::new(file)), &parse_options)
+                    .map_err(|msg| msg.to_string())?
+            } else {
+                Disasm::from_reader(BufReader::new(file), &parse_options)
+                    .map_err(|msg| msg.to_string())?
+            }
+        }
+        (None, [path_obj_file]) if path_obj_file == "-" => {
+            if cli.path_objdump.is_some() {
+                return Err(
+                    "Reading objdump output from stdin can't be combined with an objdump executable"
+                        .to_string(),
+                );
+            }
+            Disasm::from_reader(io::stdin().lock(), &parse_options)
+                .map_err(|msg| msg.to_string())?
+        }
+        (None, []) => unreachable!("clap requires either an object file or a dump file"),
+        (None, path_obj_files) => {
+            if path_obj_files.iter().any(|path| path == "-") {
+                return Err(
+                    "Reading objdump output from stdin can't be combined with other object files"
+                        .to_string(),
+                );
+            }
+            // Sort only once all the object files have been merged
+            let unsorted_options = ParseOptions {
+                keep_order: true,
+                ..parse_options.clone()
+            };
+            let mut disasms = path_obj_files
+                .iter()
+                .map(|path_obj_file| {
+                    let stdout = disassemble(&cli, path_obj_file)?;
+                    let disasm = Disasm::from_lines(stdout.lines(), &unsorted_options)
+                        .map_err(|msg| msg.to_string())?;
+                    if cli.verify_filename {
+                        verify_file_name(&disasm, path_obj_file)?;
+                    }
+                    Ok(disasm)
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let mut disasm = disasms.remove(0);
+            disasm.merge(disasms);
+            if !parse_options.keep_order {
+                disasm.sort_with(parse_options.sort_by, parse_options.case_insensitive_sort);
+            }
+            disasm
+        }
+    };
+    if let Some(function) = &cli.function {
+        // GNU objdump still prints every section header when the function doesn't exist
+        if disasm.iter_symbols().next().is_none() {
+            return Err(format!("The function '{function}' was not found"));
+        }
+    }
+    if cli.strict_symbols {
+        let duplicates: Vec<String> = disasm
+            .sections()
+            .map(|sec| (sec.get_name(), sec.find_duplicate_symbols()))
+            .filter(|(_, symbols)| !symbols.is_empty())
+            .map(|(name, symbols)| format!("{name}: {}", symbols.join(", ")))
+            .collect();
+        if !duplicates.is_empty() {
+            return Err(format!(
+                "Duplicate symbol names found\n{}",
+                duplicates.join("\n")
+            ));
+        }
     }
+    if cli.parse_stats {
+        eprintln!("Parsed {}", disasm.parse_stats());
+    }
+    if !cli.quiet {
+        if !disasm.skipped_lines().is_empty() {
+            eprintln!(
+                "Skipped {} unrecognized lines",
+                disasm.skipped_lines().len()
+            );
+        }
+        for name in disasm.find_duplicate_sections() {
+            eprintln!(
+                "Section '{name}' appears more than once, use --merge-duplicate-sections to merge it"
+            );
+        }
+        for name in &cli.sections {
+            if disasm.sections().all(|sec| sec.get_name() != name) {
+                eprintln!("Section '{name}' was not found");
+            }
+        }
+    }
+    apply_filters(&cli, &mut disasm);
+    let nothing_left =
+        disasm.is_empty() || (cli.count_instructions && disasm.summary().instructions == 0);
 
-    let stdout = str::from_utf8(&objdump_res.stdout)
-        .map_err(|msg| msg.to_string())?
-        .to_string();
+    if cli.summary && matches!(cli.format, OutputFormat::Csv) {
+        return Err("The summary can't be combined with the CSV format".to_string());
+    }
+    let summary = if cli.summary {
+        format!("{}\n", disasm.summary())
+    } else {
+        String::new()
+    };
 
-    let disasm = Disasm::try_from(stdout)?.to_string();
+    let disasm = match (&cli.path_diff_obj_file, cli.format) {
+        (Some(path_diff_obj_file), _) => {
+            let stdout = disassemble(&cli, path_diff_obj_file)?;
+            let mut other = Disasm::from_lines(stdout.lines(), &parse_options)
+                .map_err(|msg| msg.to_string())?;
+            if cli.verify_filename {
+                verify_file_name(&other, path_diff_obj_file)?;
+            }
+            apply_filters(&cli, &mut other);
+            disasm.diff(&other).to_string()
+        }
+        _ if cli.callgraph => callgraph_to_dot(&disasm),
+        _ if cli.histogram => histogram_to_string(disasm.opcode_histogram()),
+        _ if cli.category_stats => category_stats_to_string(disasm.category_stats()),
+        _ if cli.count_instructions => instruction_counts_to_string(&disasm),
+        _ if cli.find_clones => clone_clusters_to_string(&disasm),
+        _ if cli.section_stats => section_stats_to_string(&disasm),
+        _ if cli.list_sections => disasm
+            .section_names()
+            .iter()
+            .map(|name| format!("{name}\n"))
+            .collect(),
+        _ if cli.list_symbols => disasm
+            .iter_symbols()
+            .map(|(section, symbol)| format!("{section}\t{symbol}\n"))
+            .collect(),
+        _ if cli.by_symbol && use_color(&cli) => {
+            let mut colored = Ansi::new(Vec::new());
+            disasm
+                .write_colored_by_symbol(&mut colored, &format_options)
+                .map_err(|msg| msg.to_string())?;
+            String::from_utf8(colored.into_inner()).map_err(|msg| msg.to_string())?
+        }
+        _ if cli.by_symbol => disasm.to_string_by_symbol(&format_options),
+        _ if cli.hash => disasm
+            .sections()
+            .flat_map(|section| {
+                section.symbols().map(move |symbol| {
+                    let hash = symbol.content_hash(cli.operands);
+                    format!(
+                        "{}/{}: {hash:016x}\n",
+                        section.get_name(),
+                        symbol.get_name()
+                    )
+                })
+            })
+            .collect(),
+        _ if cli.faithful => disasm.to_objdump_string(),
+        _ if cli.flat => summary + &disasm.to_flat_string(&format_options),
+        _ if cli.track.is_some() => track_changes(&cli, &disasm)?,
+        _ if cli.list_empty_symbols => disasm
+            .empty_symbols()
+            .iter()
+            .map(|(section, symbol)| format!("{section} {symbol}\n"))
+            .collect(),
+        (None, OutputFormat::Text) if use_color(&cli) => {
+            let mut colored = Ansi::new(Vec::new());
+            disasm
+                .write_colored(&mut colored, &format_options)
+                .map_err(|msg| msg.to_string())?;
+            summary + &String::from_utf8(colored.into_inner()).map_err(|msg| msg.to_string())?
+        }
+        (None, OutputFormat::Text) => {
+            // Stream the plain text, the largest output, instead of building it in memory
+            write_output(&cli, |mut out| {
+                out.write_all(summary.as_bytes())?;
+                disasm.write_to(&mut out, &format_options)
+            })?;
+            return Ok(exit_code(&cli, nothing_left));
+        }
+        (None, OutputFormat::Json) if cli.summary => {
+            let json = JsonWithSummary {
+                summary: disasm.summary(),
+                disasm: &disasm,
+            };
+            serde_json::to_string_pretty(&json).map_err(|msg| msg.to_string())? + "\n"
+        }
+        (None, OutputFormat::Json) => {
+            serde_json::to_string_pretty(&disasm).map_err(|msg| msg.to_string())? + "\n"
+        }
+        (None, OutputFormat::Markdown) if cli.summary => {
+            format!("{summary}\n{}", disasm.to_markdown(&format_options))
+        }
+        (None, OutputFormat::Markdown) => disasm.to_markdown(&format_options),
+        (None, OutputFormat::Csv) => {
+            let mut csv = Vec::new();
+            disasm.to_csv(&mut csv).map_err(|msg| msg.to_string())?;
+            String::from_utf8(csv).map_err(|msg| msg.to_string())?
+        }
+    };
+
+    write_output(&cli, |out| out.write_all(disasm.as_bytes()))?;
+    Ok(exit_code(&cli, nothing_left))
+}
+
+/// Create the output file and/or stdout sinks and pass them to `write`, then flush and finish them.
+///
+/// The sinks are only created once the output is ready, so a failure leaves an existing output file untouched.
+fn write_output<F>(cli: &Cli, write: F) -> Result<(), String>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let mut out_file = match &cli.path_out_file {
+        Some(path_out_file) => {
+            let file = File::create(path_out_file).map_err(|msg| msg.to_string())?;
+            Some(if cli.gzip_out {
+                OutFile::Gzip(GzEncoder::new(file, Compression::default()))
+            } else {
+                OutFile::Plain(file)
+            })
+        }
+        None => None,
+    };
+    let mut stdout = io::stdout();
+    let mut sinks: Vec<&mut dyn Write> = Vec::new();
+    if let Some(out_file) = &mut out_file {
+        sinks.push(out_file);
+    }
+    if cli.path_out_file.is_none() || cli.tee {
+        sinks.push(&mut stdout);
+    }
+    let tee: Box<dyn Write> = if cli.no_trailing_newline {
+        Box::new(NoTrailingNewline::new(Tee(sinks)))
+    } else {
+        Box::new(Tee(sinks))
+    };
+    let mut out = BufWriter::new(tee);
+    write(&mut out)
+        .and_then(|_| out.flush())
+        .map_err(|msg| msg.to_string())?;
+    // Release the borrow of the output file, everything was flushed above
+    drop(out);
+    match out_file {
+        Some(out_file) => out_file.finish().map_err(|msg| msg.to_string()),
+        None => Ok(()),
+    }
+}
 
-    match cli.path_out_file {
-        Some(file) => write(file, disasm).map_err(|msg| msg.to_string()),
-        None => io::stdout()
-            .write_all(disasm.as_bytes())
-            .map_err(|msg| msg.to_string()),
+/// Exit code of a successful run, warning when nothing was left to print unless allowed.
+fn exit_code(cli: &Cli, nothing_left: bool) -> ExitCode {
+    if nothing_left && !cli.allow_empty {
+        if !cli.quiet {
+            eprintln!(
+                "Nothing left to print after filtering, use --allow-empty to exit successfully"
+            );
+        }
+        return ExitCode::from(2);
     }
+    ExitCode::SUCCESS
 }
@@ -0,0 +1,482 @@
+/*
+ * This file is part of Disasm-Util.
+ *
+ * Copyright 2022 SECO Mind Srl
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ *
+ * SPDX-License-Identifier: Apache-2.0
+ */
+
+use std::fs;
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+const DUMP: &str = "
+example.o:     file format elf64-x86-64
+
+
+Disassembly of section .text:
+
+<main>:
+\tpush   %rbp
+\tret
+";
+
+/// Write `DUMP` to a temporary file.
+fn plain_dump(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("disasm-util-{}-{name}", std::process::id()));
+    fs::write(&path, DUMP).unwrap();
+    path
+}
+
+/// Write `DUMP` gzip-compressed to a temporary file.
+fn gzipped_dump(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("disasm-util-{}-{name}", std::process::id()));
+    let mut encoder = GzEncoder::new(fs::File::create(&path).unwrap(), Compression::default());
+    encoder.write_all(DUMP.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    path
+}
+
+fn run_with_dump(dump: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_disasm-util"))
+        .arg("--input-dump")
+        .arg(dump)
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Create an executable shell script standing in for objdump.
+#[cfg(unix)]
+fn fake_objdump(name: &str, script: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("disasm-util-{}-{name}", std::process::id()));
+    fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[cfg(unix)]
+fn run_with_objdump(objdump: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_disasm-util"))
+        .arg("-e")
+        .arg(objdump)
+        .args(args)
+        .arg("example_obj/hello_world.o")
+        .output()
+        .unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn objdump_failing_without_stderr_err() {
+    let objdump = fake_objdump("exit-3", "exit 3");
+    let output = run_with_objdump(&objdump, &[]);
+    fs::remove_file(objdump).unwrap();
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("exit status: 3"), "{stderr}");
+}
+
+#[cfg(unix)]
+#[test]
+fn objdump_warning_on_stderr_ok() {
+    let objdump = fake_objdump(
+        "warning",
+        "echo 'objdump: warning: benign' >&2\nexec objdump \"$@\"",
+    );
+    let output = run_with_objdump(&objdump, &[]);
+    fs::remove_file(objdump).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        fs::read("example_obj/hello_world.dis").unwrap()
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr, "objdump: warning: benign\n");
+}
+
+#[test]
+fn gzipped_dump_by_extension_ok() {
+    let dump = gzipped_dump("dump.txt.gz");
+    let output = run_with_dump(&dump, &[]);
+    fs::remove_file(dump).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        b".text:\n    <main>:\n        push\n        ret\n"
+    );
+}
+
+#[test]
+fn no_trailing_newline_ok() {
+    let dump = plain_dump("no-newline");
+    let output = run_with_dump(&dump, &["--no-trailing-newline"]);
+    fs::remove_file(dump).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stdout.last(), Some(&b't'));
+    assert_eq!(
+        output.stdout,
+        b".text:\n    <main>:\n        push\n        ret"
+    );
+}
+
+#[test]
+fn gzipped_dump_with_flag_ok() {
+    let dump = gzipped_dump("dump.txt");
+    let plain = run_with_dump(&dump, &[]);
+    let output = run_with_dump(&dump, &["--gzip"]);
+    fs::remove_file(dump).unwrap();
+
+    assert!(!plain.status.success());
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        b".text:\n    <main>:\n        push\n        ret\n"
+    );
+}
+
+#[test]
+fn code_section_regex_without_only_code_err() {
+    let dump = std::env::temp_dir().join(format!("disasm-util-{}-code-regex", std::process::id()));
+    fs::write(&dump, DUMP).unwrap();
+    let output = run_with_dump(&dump, &["--code-section-regex", r"^\.text$"]);
+    let only_code = run_with_dump(&dump, &["--only-code", "--code-section-regex", r"^\.text$"]);
+    fs::remove_file(dump).unwrap();
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--only-code"), "{stderr}");
+    assert!(only_code.status.success());
+}
+
+#[test]
+fn quiet_lenient_no_stderr_ok() {
+    let dump = std::env::temp_dir().join(format!("disasm-util-{}-lenient", std::process::id()));
+    fs::write(&dump, format!("{DUMP}\tgarbage line with @@@\n")).unwrap();
+    let verbose = run_with_dump(&dump, &["--lenient"]);
+    let quiet = run_with_dump(&dump, &["--lenient", "--quiet"]);
+    fs::remove_file(dump).unwrap();
+
+    assert!(verbose.status.success());
+    assert_eq!(verbose.stderr, b"Skipped 1 unrecognized lines\n");
+    assert!(quiet.status.success());
+    assert!(quiet.stderr.is_empty());
+    assert_eq!(quiet.stdout, verbose.stdout);
+}
+
+#[test]
+fn tee_to_file_and_stdout_ok() {
+    let dump = gzipped_dump("tee.gz");
+    let out = std::env::temp_dir().join(format!("disasm-util-{}-tee.out", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_disasm-util"))
+        .arg("--input-dump")
+        .arg(&dump)
+        .arg("-o")
+        .arg(&out)
+        .arg("--tee")
+        .output()
+        .unwrap();
+    let written = fs::read(&out).unwrap();
+    fs::remove_file(dump).unwrap();
+    fs::remove_file(out).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(written, b".text:\n    <main>:\n        push\n        ret\n");
+    assert_eq!(output.stdout, written);
+}
+
+#[cfg(unix)]
+#[test]
+fn verify_filename_mismatch_err() {
+    let objdump = fake_objdump(
+        "mismatch",
+        "printf 'other.o:     file format elf64-x86-64\\nDisassembly of section .text:\\n<main>:\\n'",
+    );
+    let unverified = run_with_objdump(&objdump, &[]);
+    let verified = run_with_objdump(&objdump, &["--verify-filename"]);
+    fs::remove_file(objdump).unwrap();
+
+    assert!(unverified.status.success());
+    assert!(!verified.status.success());
+    let stderr = String::from_utf8(verified.stderr).unwrap();
+    assert!(
+        stderr.contains("'other.o' instead of 'example_obj/hello_world.o'"),
+        "{stderr}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn verify_filename_match_ok() {
+    let output = Command::new(env!("CARGO_BIN_EXE_disasm-util"))
+        .args(["--verify-filename", "example_obj/hello_world.o"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn windows_path_normalized_ok() {
+    let objdump = fake_objdump(
+        "windows-path",
+        "printf 'C:\\\\build\\\\hello_world.o:     file format pe-x86-64\\nDisassembly of section .text:\\n<main>:\\n'",
+    );
+    let output = run_with_objdump(
+        &objdump,
+        &[
+            "--verify-filename",
+            "--normalize-path-sep",
+            "--format",
+            "json",
+        ],
+    );
+    fs::remove_file(objdump).unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\"C:/build/hello_world.o\""), "{stdout}");
+}
+
+#[test]
+fn gzip_out_round_trip_ok() {
+    let dump = gzipped_dump("gzip-out-dump.gz");
+    let out = std::env::temp_dir().join(format!("disasm-util-{}-out.gz", std::process::id()));
+    let output = run_with_dump(&dump, &["--gzip-out", "-o", out.to_str().unwrap()]);
+    let mut decoded = String::new();
+    GzDecoder::new(fs::File::open(&out).unwrap())
+        .read_to_string(&mut decoded)
+        .unwrap();
+    fs::remove_file(dump).unwrap();
+    fs::remove_file(out).unwrap();
+
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+    assert_eq!(decoded, ".text:\n    <main>:\n        push\n        ret\n");
+}
+
+#[test]
+fn all_sections_filtered_out_exit_code() {
+    let dump = gzipped_dump("filtered.gz");
+    let filtered = run_with_dump(&dump, &["--section", ".data"]);
+    let allowed = run_with_dump(&dump, &["--section", ".data", "--allow-empty"]);
+    fs::remove_file(dump).unwrap();
+
+    assert_eq!(filtered.status.code(), Some(2));
+    assert!(filtered.stdout.is_empty());
+    assert!(allowed.status.success());
+    assert!(allowed.stdout.is_empty());
+}
+
+#[test]
+fn failed_run_keeps_out_file_ok() {
+    let tmp = |name: &str| {
+        std::env::temp_dir().join(format!("disasm-util-{}-{name}", std::process::id()))
+    };
+    let dump = gzipped_dump("kept.gz");
+    let (state, out) = (tmp("kept.json"), tmp("kept.txt"));
+    fs::write(&state, "not json").unwrap();
+    fs::write(&out, "previous").unwrap();
+    let output = run_with_dump(
+        &dump,
+        &[
+            "--track",
+            state.to_str().unwrap(),
+            "-o",
+            out.to_str().unwrap(),
+        ],
+    );
+    let kept = fs::read_to_string(&out).unwrap();
+    fs::remove_file(dump).unwrap();
+    fs::remove_file(state).unwrap();
+    fs::remove_file(out).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(kept, "previous");
+}
+
+#[test]
+fn gzip_out_nothing_left_complete_ok() {
+    let dump = gzipped_dump("gzip-out-empty-dump.gz");
+    let out = std::env::temp_dir().join(format!("disasm-util-{}-empty.gz", std::process::id()));
+    let output = run_with_dump(
+        &dump,
+        &[
+            "--section",
+            ".data",
+            "--gzip-out",
+            "-o",
+            out.to_str().unwrap(),
+        ],
+    );
+    let mut decoded = String::new();
+    let read = GzDecoder::new(fs::File::open(&out).unwrap()).read_to_string(&mut decoded);
+    fs::remove_file(dump).unwrap();
+    fs::remove_file(out).unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert!(read.is_ok(), "{read:?}");
+    assert!(decoded.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn cache_dir_reused_ok() {
+    let marker = std::env::temp_dir().join(format!("disasm-util-{}-ran", std::process::id()));
+    let cache_dir = std::env::temp_dir().join(format!("disasm-util-{}-cache", std::process::id()));
+    // Fail when objdump is run a second time
+    let objdump = fake_objdump(
+        "cached",
+        &format!(
+            "[ -e {0} ] && exit 1\ntouch {0}\nexec objdump \"$@\"",
+            marker.display()
+        ),
+    );
+    let args = ["--cache-dir", cache_dir.to_str().unwrap()];
+    let first = run_with_objdump(&objdump, &args);
+    let second = run_with_objdump(&objdump, &args);
+    let uncached = run_with_objdump(&objdump, &[]);
+    fs::remove_file(objdump).unwrap();
+    fs::remove_file(marker).unwrap();
+    fs::remove_dir_all(cache_dir).unwrap();
+
+    assert!(first.status.success());
+    assert!(second.status.success());
+    assert_eq!(second.stdout, first.stdout);
+    assert!(!uncached.status.success());
+}
+
+#[cfg(unix)]
+#[test]
+fn show_tool_version_ok() {
+    let objdump = fake_objdump(
+        "version",
+        "[ \"$1\" = --version ] && echo 'GNU objdump (Fake) 9.99' && echo 'Copyright' && exit 0\nexec objdump \"$@\"",
+    );
+    let output = run_with_objdump(&objdump, &["--show-tool-version"]);
+    fs::remove_file(objdump).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stderr, b"GNU objdump (Fake) 9.99\n");
+    assert_eq!(
+        output.stdout,
+        fs::read("example_obj/hello_world.dis").unwrap()
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn no_addresses_unsupported_retried_ok() {
+    let objdump = fake_objdump(
+        "old-binutils",
+        "for arg in \"$@\"; do\n  if [ \"$arg\" = --no-addresses ]; then\n    echo \"objdump: unrecognized option '--no-addresses'\" >&2\n    exit 1\n  fi\ndone\nexec objdump \"$@\"",
+    );
+    let output = run_with_objdump(&objdump, &[]);
+    fs::remove_file(objdump).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        fs::read("example_obj/hello_world.dis").unwrap()
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("doesn't support --no-addresses"),
+        "{stderr}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn function_passed_to_objdump_ok() {
+    let objdump = fake_objdump(
+        "function",
+        "printf 'hello_world.o:     file format elf64-x86-64\\nDisassembly of section .text:\\n'\n\
+         for arg in \"$@\"; do\n\
+           [ \"$arg\" = -d ] && exit 1\n\
+           [ \"$arg\" = --disassemble=main ] && printf '<main>:\\n\\tret\\n'\n\
+         done\n\
+         exit 0",
+    );
+    let found = run_with_objdump(&objdump, &["--function", "main"]);
+    let missing = run_with_objdump(&objdump, &["--function", "missing"]);
+    fs::remove_file(objdump).unwrap();
+
+    assert!(found.status.success());
+    assert_eq!(found.stdout, b".text:\n    <main>:\n        ret\n");
+    assert!(!missing.status.success());
+    let stderr = String::from_utf8(missing.stderr).unwrap();
+    assert!(stderr.contains("'missing' was not found"), "{stderr}");
+}
+
+#[test]
+fn print_command_ok() {
+    let output = Command::new(env!("CARGO_BIN_EXE_disasm-util"))
+        .args([
+            "--print-command",
+            "--intel",
+            "--relocations",
+            "--function",
+            "main",
+            "-e",
+            "example_obj/hello_world.dis",
+            "example_obj/hello_world.o",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "example_obj/hello_world.dis --disassemble=main --no-addresses --no-show-raw-insn -r -M intel \
+         example_obj/hello_world.o\n"
+    );
+}
+
+#[test]
+fn track_reports_changed_symbols_ok() {
+    let tmp = |name: &str| {
+        std::env::temp_dir().join(format!("disasm-util-{}-{name}", std::process::id()))
+    };
+    let (dump, state) = (tmp("track.dis"), tmp("track.json"));
+    let track = |dump_text: &str| {
+        fs::write(&dump, dump_text).unwrap();
+        run_with_dump(&dump, &["--track", state.to_str().unwrap()])
+    };
+    let second_dump = format!("{DUMP}<helper>:\n\tret\n").replace("push   %rbp", "nop");
+    let first = track(DUMP);
+    let second = track(&second_dump);
+    let unchanged = track(&second_dump);
+    fs::remove_file(dump).unwrap();
+    fs::remove_file(state).unwrap();
+
+    assert!(first.status.success());
+    assert_eq!(first.stdout, b".text:\n    + <main>\n");
+    assert_eq!(second.stdout, b".text:\n    + <helper>\n    ~ <main>\n");
+    assert!(unchanged.status.success());
+    assert!(unchanged.stdout.is_empty());
+}